@@ -0,0 +1,56 @@
+extern crate raster;
+
+use raster::game::*;
+use raster::data::*;
+use raster::headless::*;
+use raster::texture::*;
+
+use std::f32::consts::PI;
+use std::path::Path;
+use std::time::Instant;
+
+const FRAMES: usize = 200;
+
+// 64x64 RGB24 noise, big enough not to fit a few cache lines
+fn texture() -> Vec<u8> {
+  let mut seed = 12345u32;
+  (0..64 * 64 * 3)
+    .map(|_| {
+      seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+      (seed >> 16) as u8
+    })
+    .collect()
+}
+
+// average frame time in milliseconds
+fn bench(game: &mut Game, spans: bool) -> f64 {
+  game.renderer.spans = spans;
+
+  let start = Instant::now();
+  for i in 0..FRAMES {
+    let dir = i as f32 / FRAMES as f32 * 2.0 * PI;
+    render_frame(game, Point3 { x: 1.5, y: 3.0, z: 0.8 }, dir);
+  }
+
+  let elapsed = start.elapsed();
+  let secs = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+  secs * 1000.0 / FRAMES as f64
+}
+
+fn main() {
+  let pixels = texture();
+  let textures = (0..4).map(|_| Texture::new(&pixels, 64, 64, 64 * 3, Format::Rgb24).unwrap()).collect();
+
+  let level = Path::new(env!("CARGO_MANIFEST_DIR")).join("data").join("map.json");
+  let mut game = Game::new(640, 480, textures, level.to_str().unwrap().to_owned()).unwrap();
+
+  // warm up
+  bench(&mut game, true);
+
+  let pixels = bench(&mut game, false);
+  let spans = bench(&mut game, true);
+
+  println!("per pixel: {:.3} ms/frame", pixels);
+  println!("spans:     {:.3} ms/frame", spans);
+  println!("speedup:   {:.2}x", pixels / spans);
+}
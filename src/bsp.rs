@@ -0,0 +1,227 @@
+use data::*;
+use util::*;
+
+use serde_json;
+
+use std::cmp;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::mem;
+
+// distance from a splitting line treated as lying on it
+const EPS: f32 = 1e-4;
+// number of candidate splitters tried per node
+const CANDIDATES: usize = 16;
+
+#[derive(Serialize, Deserialize)]
+pub struct Node {
+  // splitting line, front is on its left side
+  pub line: ([f32; 2], [f32; 2]),
+  // walls lying on the line
+  pub walls: Vec<usize>,
+  pub front: Option<Box<Node>>,
+  pub back: Option<Box<Node>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BspTree {
+  // wall pieces after splitting
+  pub walls: Vec<Wall>,
+  // index of the level wall each piece comes from
+  pub sources: Vec<usize>,
+  pub root: Option<Box<Node>>,
+}
+
+enum Side {
+  Front,
+  Back,
+  On,
+  Both,
+}
+
+// signed distance-like value of p from the line, positive on the left
+fn side_of(line: &([f32; 2], [f32; 2]), p: &[f32; 2]) -> f32 {
+  let d = vec_sub(&line.1, &line.0);
+  vec_cross(&d, &vec_sub(p, &line.0)) / vec_len(&line.0, &line.1)
+}
+
+fn classify(line: &([f32; 2], [f32; 2]), w: &Wall) -> Side {
+  let s1 = side_of(line, &[w.x0, w.y0]);
+  let s2 = side_of(line, &[w.x1, w.y1]);
+
+  if s1.abs() < EPS && s2.abs() < EPS {
+    Side::On
+  } else if s1 > -EPS && s2 > -EPS {
+    Side::Front
+  } else if s1 < EPS && s2 < EPS {
+    Side::Back
+  } else {
+    Side::Both
+  }
+}
+
+// splits the wall where it crosses the line, keeps texture in place
+fn split(line: &([f32; 2], [f32; 2]), w: &Wall) -> (Wall, Wall) {
+  let s1 = side_of(line, &[w.x0, w.y0]);
+  let s2 = side_of(line, &[w.x1, w.y1]);
+  let k = s1 / (s1 - s2);
+
+  let x = w.x0 + (w.x1 - w.x0) * k;
+  let y = w.y0 + (w.y1 - w.y0) * k;
+
+  let mut first = w.clone();
+  first.x1 = x;
+  first.y1 = y;
+
+  let mut second = w.clone();
+  second.x0 = x;
+  second.y0 = y;
+  second.u_offset += vec_len(&[w.x0, w.y0], &[x, y]);
+
+  // front piece goes first
+  if s1 > 0.0 { (first, second) } else { (second, first) }
+}
+
+impl BspTree {
+  // walls listed in skip are left out, they move and are drawn on their own
+  pub fn build(walls: &[Wall], skip: &[usize]) -> BspTree {
+    let mut tree = BspTree {
+      walls: vec![],
+      sources: vec![],
+      root: None,
+    };
+
+    let pieces: Vec<(Wall, usize)> = walls.iter()
+      .cloned()
+      .enumerate()
+      .map(|(i, w)| (w, i))
+      .filter(|p| !skip.contains(&p.1))
+      .filter(|p| vec_len(&[p.0.x0, p.0.y0], &[p.0.x1, p.0.y1]) > EPS)
+      .collect();
+
+    tree.root = tree.build_node(pieces);
+    tree
+  }
+
+  fn build_node(&mut self, pieces: Vec<(Wall, usize)>) -> Option<Box<Node>> {
+    if pieces.is_empty() {
+      return None;
+    }
+
+    // splitter causing the fewest splits and the best balance
+    let step = cmp::max(pieces.len() / CANDIDATES, 1);
+    let best = (0..pieces.len())
+      .step_by(step)
+      .min_by_key(|&i| {
+        let w = &pieces[i].0;
+        let line = ([w.x0, w.y0], [w.x1, w.y1]);
+
+        let (mut front, mut back, mut splits) = (0isize, 0isize, 0isize);
+        for p in &pieces {
+          match classify(&line, &p.0) {
+            Side::Front => front += 1,
+            Side::Back => back += 1,
+            Side::Both => splits += 1,
+            Side::On => {}
+          }
+        }
+
+        splits * 8 + (front - back).abs()
+      })
+      .unwrap();
+
+    let line = {
+      let w = &pieces[best].0;
+      ([w.x0, w.y0], [w.x1, w.y1])
+    };
+
+    let mut on = vec![];
+    let mut front = vec![];
+    let mut back = vec![];
+
+    for (w, source) in pieces {
+      match classify(&line, &w) {
+        Side::On => on.push((w, source)),
+        Side::Front => front.push((w, source)),
+        Side::Back => back.push((w, source)),
+        Side::Both => {
+          let (f, b) = split(&line, &w);
+          front.push((f, source));
+          back.push((b, source));
+        }
+      }
+    }
+
+    let mut indices = vec![];
+    for (w, source) in on {
+      indices.push(self.walls.len());
+      self.walls.push(w);
+      self.sources.push(source);
+    }
+
+    Some(Box::new(Node {
+      line: line,
+      walls: indices,
+      front: self.build_node(front),
+      back: self.build_node(back),
+    }))
+  }
+
+  pub fn save(&self, path: &str) -> io::Result<()> {
+    let data = serde_json::to_string(self)
+      .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    File::create(path)?.write_all(data.as_bytes())
+  }
+
+  pub fn load(path: &str) -> io::Result<BspTree> {
+    let mut data = String::new();
+    File::open(path)?.read_to_string(&mut data)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+  }
+
+  // moves all pieces of a level wall vertically
+  pub fn set_height(&mut self, source: usize, z: f32, h: f32) {
+    for (w, &s) in self.walls.iter_mut().zip(self.sources.iter()) {
+      if s == source {
+        w.z = z;
+        w.h = h;
+      }
+    }
+  }
+
+  // calls f for every wall piece, nearest first, until it returns false
+  pub fn walk<F>(&self, p: &[f32; 2], f: &mut F)
+    where F: FnMut(&Wall) -> bool
+  {
+    if let Some(ref root) = self.root {
+      self.walk_node(root, p, f);
+    }
+  }
+
+  fn walk_node<F>(&self, node: &Node, p: &[f32; 2], f: &mut F) -> bool
+    where F: FnMut(&Wall) -> bool
+  {
+    let (mut near, mut far) = (&node.front, &node.back);
+    if side_of(&node.line, p) < 0.0 {
+      mem::swap(&mut near, &mut far);
+    }
+
+    if let Some(ref n) = *near {
+      if !self.walk_node(n, p, f) {
+        return false;
+      }
+    }
+
+    for &i in &node.walls {
+      if !f(&self.walls[i]) {
+        return false;
+      }
+    }
+
+    match *far {
+      Some(ref n) => self.walk_node(n, p, f),
+      None => true,
+    }
+  }
+}
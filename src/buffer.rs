@@ -5,6 +5,8 @@ pub struct Buffer {
   pub zbuffer: Vec<f32>,
   pub width: usize,
   pub height: usize,
+  // first screen column, strips of the screen start further right
+  pub x0: usize,
 }
 
 impl Buffer {
@@ -14,22 +16,42 @@ impl Buffer {
       zbuffer: vec![0.0f32; width * height],
       width: width,
       height: height,
+      x0: 0,
+    }
+  }
+
+  // columns x0..x0 + width of the screen
+  pub fn strip(x0: usize, width: usize, height: usize) -> Buffer {
+    let mut buffer = Buffer::new(width, height);
+    buffer.x0 = x0;
+    buffer
+  }
+
+  // copies the strip into the same columns of this buffer
+  pub fn copy_strip(&mut self, strip: &Buffer) {
+    let w = strip.width;
+    for y in 0..strip.height {
+      let dst = strip.x0 + y * self.width;
+      let src = y * w;
+      self.pixels[dst * 3..(dst + w) * 3].copy_from_slice(&strip.pixels[src * 3..(src + w) * 3]);
+      self.zbuffer[dst..dst + w].copy_from_slice(&strip.zbuffer[src..src + w]);
     }
   }
 
   pub fn set_pixel(&mut self, x: usize, y: usize, rgb: [u8; 3]) {
-    self.pixels[(x + y * self.width) * 3 + 0] = rgb[0];
-    self.pixels[(x + y * self.width) * 3 + 1] = rgb[1];
-    self.pixels[(x + y * self.width) * 3 + 2] = rgb[2];
+    let i = x - self.x0 + y * self.width;
+    self.pixels[i * 3 + 0] = rgb[0];
+    self.pixels[i * 3 + 1] = rgb[1];
+    self.pixels[i * 3 + 2] = rgb[2];
   }
 
   pub fn set_pixel3(&mut self, x: usize, y: usize, z: f32, rgb: [u8; 3]) {
     self.set_pixel(x, y, rgb);
-    self.zbuffer[x + y * self.width] = z;
+    self.zbuffer[x - self.x0 + y * self.width] = z;
   }
 
   pub fn depth(&mut self, x: usize, y: usize) -> f32 {
-    self.zbuffer[x + y * self.width]
+    self.zbuffer[x - self.x0 + y * self.width]
   }
 
   pub fn clear(&mut self) {
@@ -0,0 +1,210 @@
+use data::*;
+use util::*;
+
+// max number of slide iterations per move
+const MAX_SLIDES: usize = 4;
+// distance kept between the player and a wall after a hit
+const SKIN: f32 = 0.001;
+
+// wall blocks a body spanning [feet, head] vertically
+pub fn blocks(w: &Wall, feet: f32, head: f32) -> bool {
+  w.h > 0.0 && w.z < head && w.z + w.h > feet
+}
+
+// segments blocking a body standing at feet, which can step
+// over anything lower than feet + step
+pub fn blocking_segments(walls: &[Wall],
+                         floors: &[Floor],
+                         sectors: &[Sector],
+                         feet: f32,
+                         head: f32,
+                         step: f32)
+                         -> Vec<([f32; 2], [f32; 2])> {
+  let mut segments = vec![];
+
+  for w in walls.iter().filter(|w| blocks(w, feet + step, head)) {
+    segments.push(([w.x0, w.y0], [w.x1, w.y1]));
+  }
+
+  // raised floors act as solid blocks standing on the ground,
+  // low ceilings as blocks hanging from the sky
+  let solid = |f: &&Floor| if f.ceiling { f.height < head } else { f.height > feet + step };
+
+  for f in floors.iter().filter(solid) {
+    for points in Some(&f.points).into_iter().chain(f.holes.iter()) {
+      let seq = points.iter().zip(points.iter().cycle().skip(1));
+      for (p1, p2) in seq {
+        segments.push(([p1.x, p1.y], [p2.x, p2.y]));
+      }
+    }
+  }
+
+  // solid sector edges and portals we can not pass through
+  for s in sectors {
+    for (i, e) in s.edges.iter().enumerate() {
+      let open = e.portal.is_some_and(|p| {
+        let n = &sectors[p];
+        n.floor <= feet + step && n.ceiling >= head
+      });
+
+      if !open {
+        segments.push(s.edge(i));
+      }
+    }
+  }
+
+  segments
+}
+
+// height of the highest floor under (x, y) not higher than max,
+// base is the height of the ground or the sector floor
+pub fn ground_height(floors: &[Floor], x: f32, y: f32, max: f32, base: f32) -> f32 {
+  floors.iter()
+    .filter(|f| !f.ceiling && f.height <= max && f.contains(&[x, y]))
+    .fold(base, |h, f| f32::max(h, f.height))
+}
+
+// height of the lowest ceiling over (x, y) not lower than min,
+// base is the height of the sky or the sector ceiling
+pub fn ceiling_height(floors: &[Floor], x: f32, y: f32, min: f32, base: f32) -> f32 {
+  floors.iter()
+    .filter(|f| f.ceiling && f.height >= min && f.contains(&[x, y]))
+    .fold(base, |h, f| f32::min(h, f.height))
+}
+
+// time of impact in [0, 1] and contact normal of a circle
+// moving from p by d against segment (a, b)
+fn sweep_segment(p: [f32; 2],
+                 d: [f32; 2],
+                 r: f32,
+                 a: [f32; 2],
+                 b: [f32; 2])
+                 -> Option<(f32, [f32; 2])> {
+  let ab = vec_sub(&b, &a);
+  let len = vec_len(&a, &b);
+
+  let mut hit: Option<(f32, [f32; 2])> = None;
+
+  // segment face
+  if len > 0.0 {
+    let mut n = [-ab[1] / len, ab[0] / len];
+    let mut dist = vec_dot(&vec_sub(&p, &a), &n);
+    if dist < 0.0 {
+      n = [-n[0], -n[1]];
+      dist = -dist;
+    }
+
+    let dn = vec_dot(&d, &n);
+    if dn < 0.0 {
+      let t = f32::max((dist - r) / -dn, 0.0);
+      if t <= 1.0 {
+        let c = [p[0] + d[0] * t, p[1] + d[1] * t];
+        let s = vec_dot(&vec_sub(&c, &a), &ab) / (len * len);
+        if (0.0..=1.0).contains(&s) {
+          hit = Some((t, n));
+        }
+      }
+    }
+  }
+
+  // segment ends
+  for e in &[a, b] {
+    let m = vec_sub(&p, e);
+    let bq = vec_dot(&m, &d);
+    // moving away from the end point
+    if bq >= 0.0 {
+      continue;
+    }
+
+    let aq = vec_dot(&d, &d);
+    let cq = vec_dot(&m, &m) - r * r;
+
+    let t = if cq <= 0.0 {
+      0.0
+    } else {
+      let disc = bq * bq - aq * cq;
+      if disc < 0.0 {
+        continue;
+      }
+      (-bq - f32::sqrt(disc)) / aq
+    };
+
+    if t > 1.0 || hit.is_some_and(|(ht, _)| ht <= t) {
+      continue;
+    }
+
+    let c = [p[0] + d[0] * t - e[0], p[1] + d[1] * t - e[1]];
+    let cl = vec_len(&c, &[0.0, 0.0]);
+    if cl > 0.0 {
+      hit = Some((t, [c[0] / cl, c[1] / cl]));
+    }
+  }
+
+  hit
+}
+
+// closest point on segment (a, b) to p
+pub fn closest_point(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+  let ab = vec_sub(&b, &a);
+  let l2 = vec_dot(&ab, &ab);
+  if l2 == 0.0 {
+    return a;
+  }
+
+  let s = clamp_f(vec_dot(&vec_sub(&p, &a), &ab) / l2, 0.0, 1.0);
+  [a[0] + ab[0] * s, a[1] + ab[1] * s]
+}
+
+// moves a circle of radius r from p by d, sliding along segments
+pub fn move_circle(segments: &[([f32; 2], [f32; 2])],
+                   p: [f32; 2],
+                   d: [f32; 2],
+                   r: f32)
+                   -> [f32; 2] {
+  let mut pos = p;
+  let mut delta = d;
+
+  for _ in 0..MAX_SLIDES {
+    if delta[0] == 0.0 && delta[1] == 0.0 {
+      break;
+    }
+
+    let mut first: Option<(f32, [f32; 2])> = None;
+    for &(a, b) in segments {
+      let hit = sweep_segment(pos, delta, r, a, b);
+      if let Some((t, n)) = hit {
+        if first.is_none_or(|(ft, _)| t < ft) {
+          first = Some((t, n));
+        }
+      }
+    }
+
+    match first {
+      None => {
+        pos = [pos[0] + delta[0], pos[1] + delta[1]];
+        break;
+      }
+      Some((t, n)) => {
+        pos = [pos[0] + delta[0] * t + n[0] * SKIN,
+               pos[1] + delta[1] * t + n[1] * SKIN];
+
+        // slide along the wall with what is left of the move
+        let rest = [delta[0] * (1.0 - t), delta[1] * (1.0 - t)];
+        let rn = vec_dot(&rest, &n);
+        delta = [rest[0] - n[0] * rn, rest[1] - n[1] * rn];
+      }
+    }
+  }
+
+  // push out of walls we already overlap
+  for &(a, b) in segments {
+    let c = closest_point(pos, a, b);
+    let dist = vec_len(&pos, &c);
+    if dist < r && dist > 0.0 {
+      let k = (r + SKIN - dist) / dist;
+      pos = [pos[0] + (pos[0] - c[0]) * k, pos[1] + (pos[1] - c[1]) * k];
+    }
+  }
+
+  pos
+}
@@ -1,4 +1,20 @@
-#[derive(Deserialize)]
+use util::*;
+
+fn one() -> f32 {
+  1.0
+}
+
+// which wall edge the texture is anchored to
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum Peg {
+  #[default]
+  #[serde(rename = "top")]
+  Top,
+  #[serde(rename = "bottom")]
+  Bottom,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Wall {
   pub x0: f32,
   pub y0: f32,
@@ -8,6 +24,22 @@ pub struct Wall {
   pub h: f32,
   pub tex: usize,
   pub color: [u8; 3],
+
+  // texture mapping, offsets are in world units
+  #[serde(default)]
+  pub u_offset: f32,
+  #[serde(default)]
+  pub v_offset: f32,
+  #[serde(default = "one")]
+  pub u_scale: f32,
+  #[serde(default = "one")]
+  pub v_scale: f32,
+  #[serde(default)]
+  pub peg: Peg,
+
+  // one-sided walls are only visible from their left side
+  #[serde(default = "yes")]
+  pub two_sided: bool,
 }
 
 #[derive(Deserialize)]
@@ -16,24 +48,60 @@ pub struct Point2 {
   pub y: f32,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Copy)]
 pub struct Point3 {
   pub x: f32,
   pub y: f32,
   pub z: f32,
 }
 
+#[derive(Clone, Copy)]
 pub struct Player {
   pub point: Point3,
   pub dir: f32,
+  // up and down look angle, positive is up
+  pub pitch: f32,
+  // collision radius
+  pub radius: f32,
+  // eye height above the feet
+  pub height: f32,
+  // vertical speed
+  pub z_speed: f32,
 }
 
 #[derive(Deserialize)]
 pub struct Floor {
   pub points: Vec<Point2>,
+  #[serde(default)]
+  pub holes: Vec<Vec<Point2>>,
   pub height: f32,
   pub color: [u8; 3],
-  pub tex: usize
+  pub tex: usize,
+  // visible from below
+  #[serde(default)]
+  pub ceiling: bool,
+
+  // texture mapping, offsets are in world units, rotation in degrees
+  #[serde(default)]
+  pub u_offset: f32,
+  #[serde(default)]
+  pub v_offset: f32,
+  #[serde(default = "one")]
+  pub u_scale: f32,
+  #[serde(default = "one")]
+  pub v_scale: f32,
+  #[serde(default)]
+  pub rotation: f32,
+
+  // filled in when the level is loaded
+  #[serde(skip_deserializing)]
+  pub triangles: Vec<[[f32; 2]; 3]>,
+}
+
+impl Floor {
+  pub fn contains(&self, p: &[f32; 2]) -> bool {
+    point_in_polygon(p, &self.points) && !self.holes.iter().any(|h| point_in_polygon(p, h))
+  }
 }
 
 #[derive(Deserialize)]
@@ -42,3 +110,138 @@ pub struct Sprite {
   pub color: [u8; 3],
   pub tex: usize
 }
+
+// panorama wrapped around the player, drawn where nothing else is
+#[derive(Deserialize)]
+pub struct Sky {
+  #[serde(default)]
+  pub tex: Option<usize>,
+  // top and horizon colors
+  #[serde(default)]
+  pub gradient: Option<[[u8; 3]; 2]>,
+  #[serde(default)]
+  pub clouds: Option<Clouds>,
+}
+
+#[derive(Deserialize)]
+pub struct Clouds {
+  pub tex: usize,
+  // panoramas per second
+  pub speed: f32,
+  #[serde(default = "one")]
+  pub opacity: f32,
+}
+
+fn ground_tint() -> [u8; 3] {
+  [100, 255, 100]
+}
+
+fn ambient() -> [u8; 3] {
+  [0, 40, 0]
+}
+
+fn yes() -> bool {
+  true
+}
+
+#[derive(Deserialize)]
+pub struct Environment {
+  // infinite ground plane at zero height
+  #[serde(default = "yes")]
+  pub ground: bool,
+  #[serde(default)]
+  pub ground_tex: usize,
+  #[serde(default = "ground_tint")]
+  pub ground_tint: [u8; 3],
+  // color everything fades to with distance
+  #[serde(default = "ambient")]
+  pub ambient: [u8; 3],
+}
+
+impl Default for Environment {
+  fn default() -> Environment {
+    Environment {
+      ground: true,
+      ground_tex: 0,
+      ground_tint: ground_tint(),
+      ambient: ambient(),
+    }
+  }
+}
+
+// sector outline point, starts the edge going to the next point,
+// sector textures have no mapping so unknown fields are refused
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Edge {
+  pub x: f32,
+  pub y: f32,
+  // sector seen through the edge, solid wall if none
+  #[serde(default)]
+  pub portal: Option<usize>,
+  // overrides sector wall texture
+  #[serde(default)]
+  pub tex: Option<usize>,
+}
+
+// convex counterclockwise room with floor and ceiling
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sector {
+  pub edges: Vec<Edge>,
+  pub floor: f32,
+  pub ceiling: f32,
+  pub floor_tex: usize,
+  pub ceiling_tex: usize,
+  pub wall_tex: usize,
+  pub color: [u8; 3],
+}
+
+impl Sector {
+  pub fn edge(&self, i: usize) -> ([f32; 2], [f32; 2]) {
+    let e1 = &self.edges[i];
+    let e2 = &self.edges[(i + 1) % self.edges.len()];
+    ([e1.x, e1.y], [e2.x, e2.y])
+  }
+
+  pub fn contains(&self, p: &[f32; 2]) -> bool {
+    (0..self.edges.len()).all(|i| {
+      let (a, b) = self.edge(i);
+      vec_cross(&vec_sub(&b, &a), &vec_sub(p, &a)) >= 0.0
+    })
+  }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum MoverKind {
+  // wall opening upwards, its bottom rises
+  #[serde(rename = "door")]
+  Door,
+  // floor moving up or down
+  #[serde(rename = "lift")]
+  Lift,
+  // wall sliding along itself
+  #[serde(rename = "slide")]
+  Slide,
+}
+
+fn wait() -> f32 {
+  3.0
+}
+
+// geometry moved by the action key
+#[derive(Deserialize)]
+pub struct Mover {
+  pub id: String,
+  pub kind: MoverKind,
+  // wall index for doors and sliding walls, floor index for lifts
+  pub target: usize,
+  // travel when fully open, lifts go down and walls slide back
+  // when negative
+  pub distance: f32,
+  // units per second
+  pub speed: f32,
+  // seconds to stay open, stays until used again when negative
+  #[serde(default = "wait")]
+  pub wait: f32,
+}
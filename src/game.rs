@@ -1,119 +1,333 @@
 
 use render::*;
+use bsp::*;
 use data::*;
+use level::*;
+use texture::*;
+use input::*;
+use mover::*;
+use util::*;
+use collision;
 
 use std;
 
+// max up and down look angle
+const MAX_PITCH: f32 = 1.0;
+
+// fixed simulation step in seconds
+pub const TICK: f64 = 1.0 / 60.0;
+
 pub struct Game<'a> {
   pub renderer: Renderer<'a>,
 
-  pub in_fwd: bool,
-  pub in_back: bool,
-  pub in_left: bool,
-  pub in_right: bool,
-  pub in_turn_left: bool,
-  pub in_turn_right: bool,
-  pub in_action: bool,
-
   pub player: Player,
+  // pose before the last update, for interpolation
+  prev_player: Player,
+  pub step_height: f32,
+  pub gravity: f32,
+  pub jump_speed: f32,
   t: f32,
+  input: Input,
+  // action was down on the last update
+  action_held: bool,
 
   walls: Vec<Wall>,
+  bsp: BspTree,
   floors: Vec<Floor>,
   sprites: Vec<Sprite>,
+  sky: Option<Sky>,
+  environment: Environment,
+  sectors: Vec<Sector>,
+  movers: Vec<Mover>,
+  mover_states: Vec<MoverState>,
+  // walls of sliding movers, kept out of the bsp
+  sliding: Vec<usize>,
 }
 
 impl<'a> Game<'a> {
-  pub fn new(width: usize, height: usize, textures: Vec<&[u8]>, level: String) -> Game {
-    use serde_json;
-    
-    use std::fs::File;
-    use std::io::prelude::*;
-
-    let mut data = String::new();
-    let _ = File::open(level).unwrap().read_to_string(&mut data);
-
-    let root: serde_json::Value = serde_json::from_str(data.as_str()).unwrap();
-    let walls : Vec<Wall> = serde_json::from_value(root["walls"].clone()).unwrap();
-    let floors : Vec<Floor> = serde_json::from_value(root["floors"].clone()).unwrap();
-    let sprites : Vec<Sprite> = serde_json::from_value(root["sprites"].clone()).unwrap();
-    
-    Game {
-      renderer: Renderer::new(width, height, textures),
-
-      in_fwd: false,
-      in_back: false,
-      in_left: false,
-      in_right: false,
-      in_turn_left: false,
-      in_turn_right: false,
-      in_action: false,
-
-      player: Player {
-        point: Point3 {
-          x: 0.0,
-          y: 0.0,
-          z: 0.8,
-        },
-        dir: 0.0,
+  pub fn new(width: usize,
+             height: usize,
+             textures: Vec<Texture<'a>>,
+             level: String)
+             -> Result<Game<'a>, LevelError> {
+    let level = Level::load(&level, textures.len())?;
+
+    let player = Player {
+      point: Point3 {
+        x: 0.0,
+        y: 0.0,
+        z: 0.8,
       },
+      dir: 0.0,
+      pitch: 0.0,
+      radius: 0.25,
+      height: 0.8,
+      z_speed: 0.0,
+    };
+
+    let mut renderer = Renderer::new(width, height, textures);
+    renderer.ambient = level.environment.ambient;
+
+    let sliding: Vec<usize> = level.movers
+      .iter()
+      .filter(|m| m.kind == MoverKind::Slide)
+      .map(|m| m.target)
+      .collect();
+    let bsp = BspTree::build(&level.walls, &sliding);
+
+    let mover_states = level.movers
+      .iter()
+      .map(|m| MoverState::new(m, &level.walls, &level.floors))
+      .collect();
+
+    Ok(Game {
+      renderer: renderer,
+
+      player: player,
+      prev_player: player,
+      step_height: 0.35,
+      gravity: 9.8,
+      jump_speed: 3.5,
       t: 0.0,
+      input: Input::default(),
+      action_held: false,
 
-      sprites: sprites,
-      walls: walls,
-      floors: floors
-    }
+      sprites: level.sprites,
+      sky: level.sky,
+      environment: level.environment,
+      sectors: level.sectors,
+      walls: level.walls,
+      bsp: bsp,
+      movers: level.movers,
+      mover_states: mover_states,
+      sliding: sliding,
+      floors: level.floors
+    })
   }
 
   pub fn update(&mut self, dt: f64) {
     let pi = std::f32::consts::PI;
 
+    self.prev_player = self.player;
+
     let dt_ms = dt as f32;
-    if self.in_fwd {
-      self.player.point.x += f32::cos(self.player.dir) * dt_ms * 2.0;
-      self.player.point.y += f32::sin(self.player.dir) * dt_ms * 2.0;
-    }
-    if self.in_back {
-      self.player.point.x += f32::cos(self.player.dir + pi) * dt_ms * 2.0;
-      self.player.point.y += f32::sin(self.player.dir + pi) * dt_ms * 2.0;
-    }
-    if self.in_left {
-      self.player.point.x += f32::cos(self.player.dir + pi / 2.0) * dt_ms * 2.0;
-      self.player.point.y += f32::sin(self.player.dir + pi / 2.0) * dt_ms * 2.0;
+
+    // action works once per press
+    if self.input.action && !self.action_held {
+      self.use_facing();
     }
-    if self.in_right {
-      self.player.point.x += f32::cos(self.player.dir - pi / 2.0) * dt_ms * 2.0;
-      self.player.point.y += f32::sin(self.player.dir - pi / 2.0) * dt_ms * 2.0;
+    self.action_held = self.input.action;
+
+    self.update_movers(dt_ms);
+
+    let mut dx = 0.0;
+    let mut dy = 0.0;
+
+    let forward = self.input.forward * dt_ms * 2.0;
+    dx += f32::cos(self.player.dir) * forward;
+    dy += f32::sin(self.player.dir) * forward;
+
+    let strafe = self.input.strafe * dt_ms * 2.0;
+    dx += f32::cos(self.player.dir - pi / 2.0) * strafe;
+    dy += f32::sin(self.player.dir - pi / 2.0) * strafe;
+
+    let feet = self.player.point.z - self.player.height;
+    let head = self.player.point.z;
+    let segments = collision::blocking_segments(&self.walls,
+                                                &self.floors,
+                                                &self.sectors,
+                                                feet,
+                                                head,
+                                                self.step_height);
+    let pos = collision::move_circle(&segments,
+                                     [self.player.point.x, self.player.point.y],
+                                     [dx, dy],
+                                     self.player.radius);
+    self.player.point.x = pos[0];
+    self.player.point.y = pos[1];
+
+    let (floor, ceiling) = match self.sector_at(&pos) {
+      Some(i) => (self.sectors[i].floor, self.sectors[i].ceiling),
+      None => (0.0, f32::INFINITY),
+    };
+
+    // stand on the highest floor we can step onto
+    let ground = collision::ground_height(&self.floors,
+                                          pos[0],
+                                          pos[1],
+                                          feet + self.step_height,
+                                          floor);
+    let ceiling = collision::ceiling_height(&self.floors, pos[0], pos[1], head, ceiling);
+    let on_ground = feet <= ground;
+
+    if self.input.jump && on_ground {
+      self.player.z_speed = self.jump_speed;
     }
 
-    if self.in_turn_left {
-      self.player.dir += dt_ms * 2.0;
+    self.player.z_speed -= self.gravity * dt_ms;
+    let mut feet = feet + self.player.z_speed * dt_ms;
+
+    // bump the head
+    if feet + self.player.height > ceiling {
+      feet = ceiling - self.player.height;
+      self.player.z_speed = f32::min(self.player.z_speed, 0.0);
     }
-    if self.in_turn_right {
-      self.player.dir -= dt_ms * 2.0;
+
+    if feet <= ground {
+      feet = ground;
+      self.player.z_speed = 0.0;
     }
 
+    self.player.point.z = feet + self.player.height;
+
+    self.player.dir += self.input.turn * dt_ms * 2.0 + self.input.turn_delta;
+    self.player.pitch += self.input.look * dt_ms * 1.5 + self.input.look_delta;
+    self.input.turn_delta = 0.0;
+    self.input.look_delta = 0.0;
+
+    self.player.pitch = clamp_f(self.player.pitch, -MAX_PITCH, MAX_PITCH);
+
     self.t += dt_ms;
     self.renderer.t = self.t;
   }
 
-  pub fn draw(&mut self) {
-    self.renderer.buffer.clear();
-    self.renderer.draw_background(&self.player);
+  // input used by the following updates, mouse angles add up until
+  // the next update applies them
+  pub fn set_input(&mut self, input: Input) {
+    let turn_delta = self.input.turn_delta + input.turn_delta;
+    let look_delta = self.input.look_delta + input.look_delta;
 
-    for w in &self.walls {
-      self.renderer.draw_wall(w, &self.player);
+    self.input = Input {
+      turn_delta: turn_delta,
+      look_delta: look_delta,
+      ..input
+    };
+  }
+
+  // starts or reverses all movers with the id
+  pub fn activate(&mut self, id: &str) -> bool {
+    let mut found = false;
+    for (m, s) in self.movers.iter().zip(self.mover_states.iter_mut()) {
+      if m.id == id {
+        s.activate();
+        found = true;
+      }
+    }
+    found
+  }
+
+  // activates the nearest mover the player looks at, lifts also when
+  // standing on them
+  fn use_facing(&mut self) {
+    let p = [self.player.point.x, self.player.point.y];
+    let d = [f32::cos(self.player.dir), f32::sin(self.player.dir)];
+
+    let mut nearest: Option<(usize, f32)> = None;
+
+    for (i, m) in self.movers.iter().enumerate() {
+      let dist = match m.kind {
+        MoverKind::Door | MoverKind::Slide => {
+          let w = &self.walls[m.target];
+          ray_hit(&p, &d, &[w.x0, w.y0], &[w.x1, w.y1])
+        }
+        MoverKind::Lift => {
+          let f = &self.floors[m.target];
+          if f.contains(&p) {
+            Some(0.0)
+          } else {
+            let n = f.points.len();
+            (0..n)
+              .filter_map(|j| {
+                let (a, b) = (&f.points[j], &f.points[(j + 1) % n]);
+                ray_hit(&p, &d, &[a.x, a.y], &[b.x, b.y])
+              })
+              .fold(None, |min: Option<f32>, t| Some(min.map_or(t, |min| f32::min(min, t))))
+          }
+        }
+      };
+
+      if let Some(dist) = dist {
+        if dist <= USE_RANGE && nearest.is_none_or(|(_, n)| dist < n) {
+          nearest = Some((i, dist));
+        }
+      }
     }
 
-    for s in &self.sprites {
-      self.renderer.draw_sprite(s, &self.player);
+    if let Some((i, _)) = nearest {
+      self.mover_states[i].activate();
     }
+  }
+
+  fn update_movers(&mut self, dt: f32) {
+    let p = [self.player.point.x, self.player.point.y];
+    let feet = self.player.point.z - self.player.height;
+    let head = self.player.point.z;
+
+    for (m, s) in self.movers.iter().zip(self.mover_states.iter_mut()) {
+      if s.update(m, dt) {
+        s.apply(m, &mut self.walls, &mut self.floors, &mut self.bsp);
+      }
 
-    for f in &self.floors {
-      self.renderer.draw_floor(f, &self.player);
+      // walls closing on the player open again
+      if s.phase == Phase::Closing && m.kind != MoverKind::Lift {
+        let w = &self.walls[m.target];
+        let closest = collision::closest_point(p, [w.x0, w.y0], [w.x1, w.y1]);
+        let inside = vec_len(&closest, &p) < self.player.radius;
+
+        if inside && collision::blocks(w, feet + self.step_height, head) {
+          s.activate();
+        }
+      }
     }
   }
 
+  // input the next update will use
+  pub fn input(&self) -> Input {
+    self.input
+  }
+
+  fn sector_at(&self, p: &[f32; 2]) -> Option<usize> {
+    self.sectors.iter().position(|s| s.contains(p))
+  }
+
+  pub fn draw(&mut self) {
+    let player = self.player;
+    self.draw_pose(&player);
+  }
+
+  // draws the pose k of the way from the previous update to the last one
+  pub fn draw_interpolated(&mut self, k: f32) {
+    let (prev, next) = (self.prev_player, self.player);
+    let lerp = |a: f32, b: f32| a + (b - a) * k;
+
+    let mut player = next;
+    player.point.x = lerp(prev.point.x, next.point.x);
+    player.point.y = lerp(prev.point.y, next.point.y);
+    player.point.z = lerp(prev.point.z, next.point.z);
+    player.dir = lerp(prev.dir, next.dir);
+    player.pitch = lerp(prev.pitch, next.pitch);
+
+    self.draw_pose(&player);
+  }
+
+  fn draw_pose(&mut self, player: &Player) {
+    let pos = [player.point.x, player.point.y];
+
+    let scene = Scene {
+      sectors: &self.sectors,
+      sector: self.sector_at(&pos),
+      environment: &self.environment,
+      bsp: &self.bsp,
+      walls: &self.walls,
+      sliding: &self.sliding,
+      sprites: &self.sprites,
+      floors: &self.floors,
+      sky: self.sky.as_ref(),
+    };
+
+    self.renderer.draw_scene(&scene, player);
+  }
+
   pub fn get_data(&mut self) -> &[u8] {
     &self.renderer.buffer.pixels
   }
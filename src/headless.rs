@@ -0,0 +1,86 @@
+use game::*;
+use data::*;
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+
+// renders a single frame from the given pose, returns
+// tightly packed RGB24 pixels of the visible area
+pub fn render_frame(game: &mut Game, point: Point3, dir: f32) -> Vec<u8> {
+  game.player.point = point;
+  game.player.dir = dir;
+
+  current_frame(game)
+}
+
+// renders the current pose of the player
+pub fn current_frame(game: &mut Game) -> Vec<u8> {
+  game.draw();
+
+  let width = game.renderer.width;
+  let height = game.renderer.height;
+  let stride = game.renderer.buffer.width * 3;
+
+  let mut pixels = Vec::with_capacity(width * height * 3);
+  for y in 0..height {
+    let row = &game.renderer.buffer.pixels[y * stride..y * stride + width * 3];
+    pixels.extend_from_slice(row);
+  }
+
+  pixels
+}
+
+pub fn write_ppm(path: &Path, width: usize, height: usize, pixels: &[u8]) -> io::Result<()> {
+  let mut f = File::create(path)?;
+  write!(f, "P6\n{} {}\n255\n", width, height)?;
+  f.write_all(pixels)
+}
+
+pub fn read_ppm(path: &Path) -> io::Result<(usize, usize, Vec<u8>)> {
+  let mut data = vec![];
+  File::open(path)?.read_to_end(&mut data)?;
+
+  let bad = || io::Error::new(io::ErrorKind::InvalidData, "bad ppm header");
+
+  // header is "P6", width, height and max value separated by whitespace
+  let mut fields = vec![];
+  let mut pos = 0;
+  while fields.len() < 4 {
+    while pos < data.len() && (data[pos] as char).is_whitespace() {
+      pos += 1;
+    }
+    let start = pos;
+    while pos < data.len() && !(data[pos] as char).is_whitespace() {
+      pos += 1;
+    }
+    if start == pos {
+      return Err(bad());
+    }
+    fields.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+  }
+  pos += 1;
+
+  if fields[0] != "P6" || fields[3] != "255" {
+    return Err(bad());
+  }
+  let width: usize = fields[1].parse().map_err(|_| bad())?;
+  let height: usize = fields[2].parse().map_err(|_| bad())?;
+
+  if data.len() < pos + width * height * 3 {
+    return Err(bad());
+  }
+
+  Ok((width, height, data[pos..pos + width * height * 3].to_vec()))
+}
+
+// number of pixels with any channel differing by more than tolerance
+pub fn diff_pixels(a: &[u8], b: &[u8], tolerance: u8) -> usize {
+  a.chunks(3)
+    .zip(b.chunks(3))
+    .filter(|&(pa, pb)| {
+      pa.iter().zip(pb.iter()).any(|(&ca, &cb)| (ca as i16 - cb as i16).abs() > tolerance as i16)
+    })
+    .count()
+}
@@ -0,0 +1,270 @@
+use util::*;
+
+use serde_json;
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+  #[serde(rename = "move_forward")]
+  MoveForward,
+  #[serde(rename = "move_back")]
+  MoveBack,
+  #[serde(rename = "strafe_left")]
+  StrafeLeft,
+  #[serde(rename = "strafe_right")]
+  StrafeRight,
+  #[serde(rename = "turn_left")]
+  TurnLeft,
+  #[serde(rename = "turn_right")]
+  TurnRight,
+  #[serde(rename = "look_up")]
+  LookUp,
+  #[serde(rename = "look_down")]
+  LookDown,
+  #[serde(rename = "action")]
+  Action,
+  #[serde(rename = "jump")]
+  Jump,
+}
+
+// input for one update, axes are in -1..1
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug)]
+pub struct Input {
+  // forward is positive
+  pub forward: f32,
+  // right is positive
+  pub strafe: f32,
+  // turn and look rates, left and up are positive
+  pub turn: f32,
+  pub look: f32,
+  // turn and look angles from the mouse
+  pub turn_delta: f32,
+  pub look_delta: f32,
+  pub action: bool,
+  pub jump: bool,
+}
+
+impl Input {
+  // adds an action held down
+  pub fn press(&mut self, action: Action) {
+    match action {
+      Action::MoveForward => self.forward += 1.0,
+      Action::MoveBack => self.forward -= 1.0,
+      Action::StrafeLeft => self.strafe -= 1.0,
+      Action::StrafeRight => self.strafe += 1.0,
+      Action::TurnLeft => self.turn += 1.0,
+      Action::TurnRight => self.turn -= 1.0,
+      Action::LookUp => self.look += 1.0,
+      Action::LookDown => self.look -= 1.0,
+      Action::Action => self.action = true,
+      Action::Jump => self.jump = true,
+    }
+
+    self.forward = clamp_f(self.forward, -1.0, 1.0);
+    self.strafe = clamp_f(self.strafe, -1.0, 1.0);
+    self.turn = clamp_f(self.turn, -1.0, 1.0);
+    self.look = clamp_f(self.look, -1.0, 1.0);
+  }
+
+  // relative mouse motion in counts, right and down are positive
+  pub fn mouse_motion(&mut self, dx: i32, dy: i32, config: &Config) {
+    let dy = if config.mouse_invert { -dy } else { dy };
+
+    self.turn_delta -= dx as f32 * config.mouse_sensitivity;
+    self.look_delta -= dy as f32 * config.mouse_sensitivity;
+  }
+}
+
+#[derive(Deserialize)]
+pub struct Binding {
+  pub action: Action,
+  // key or controller button names as SDL spells them, e.g.
+  // "W", "Left Shift" or "leftshoulder"
+  pub keys: Vec<String>,
+}
+
+fn default_bindings() -> Vec<Binding> {
+  let bind = |action, keys: &[&str]| {
+    Binding {
+      action: action,
+      keys: keys.iter().map(|k| k.to_string()).collect(),
+    }
+  };
+
+  vec![bind(Action::MoveForward, &["W"]),
+       bind(Action::MoveBack, &["S"]),
+       bind(Action::StrafeLeft, &["A"]),
+       bind(Action::StrafeRight, &["D"]),
+       bind(Action::TurnLeft, &["Left"]),
+       bind(Action::TurnRight, &["Right"]),
+       bind(Action::LookUp, &["PageUp"]),
+       bind(Action::LookDown, &["PageDown"]),
+       bind(Action::Action, &["E"]),
+       bind(Action::Jump, &["Space"])]
+}
+
+fn default_buttons() -> Vec<Binding> {
+  let bind = |action, buttons: &[&str]| {
+    Binding {
+      action: action,
+      keys: buttons.iter().map(|b| b.to_string()).collect(),
+    }
+  };
+
+  vec![bind(Action::Action, &["x"]), bind(Action::Jump, &["a"])]
+}
+
+fn mouse_sensitivity() -> f32 {
+  0.003
+}
+
+fn dead_zone() -> f32 {
+  0.2
+}
+
+fn fov() -> f32 {
+  90.0
+}
+
+#[derive(Deserialize)]
+pub struct Config {
+  #[serde(default = "default_bindings")]
+  pub bindings: Vec<Binding>,
+  // radians per mouse count
+  #[serde(default = "mouse_sensitivity")]
+  pub mouse_sensitivity: f32,
+  #[serde(default)]
+  pub mouse_invert: bool,
+
+  #[serde(default = "default_buttons")]
+  pub buttons: Vec<Binding>,
+  // share of the stick range ignored around the center
+  #[serde(default = "dead_zone")]
+  pub dead_zone: f32,
+  #[serde(default)]
+  pub pad_invert: bool,
+
+  // horizontal field of view in degrees
+  #[serde(default = "fov")]
+  pub fov: f32,
+}
+
+impl Default for Config {
+  fn default() -> Config {
+    Config {
+      bindings: default_bindings(),
+      mouse_sensitivity: mouse_sensitivity(),
+      mouse_invert: false,
+      buttons: default_buttons(),
+      dead_zone: dead_zone(),
+      pad_invert: false,
+      fov: fov(),
+    }
+  }
+}
+
+impl Config {
+  pub fn load(path: &str) -> io::Result<Config> {
+    let mut data = String::new();
+    File::open(path)?.read_to_string(&mut data)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+  }
+
+  // input with every action whose key is down
+  pub fn input<F>(&self, is_down: F) -> Input
+    where F: Fn(&str) -> bool
+  {
+    let mut input = Input::default();
+
+    for b in &self.bindings {
+      if b.keys.iter().any(|k| is_down(k)) {
+        input.press(b.action);
+      }
+    }
+
+    input
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Axis {
+  LeftX,
+  LeftY,
+  RightX,
+  RightY,
+}
+
+// controller events as SDL reports them, axes go down and right
+pub enum PadEvent {
+  Axis(Axis, i16),
+  // button name and whether it is down
+  Button(String, bool),
+}
+
+// state of the game controllers
+#[derive(Default)]
+pub struct Gamepad {
+  axes: [i16; 4],
+  buttons: Vec<String>,
+}
+
+impl Gamepad {
+  pub fn event(&mut self, event: PadEvent) {
+    match event {
+      PadEvent::Axis(axis, value) => self.axes[axis as usize] = value,
+      PadEvent::Button(name, true) => {
+        if !self.is_down(&name) {
+          self.buttons.push(name);
+        }
+      }
+      PadEvent::Button(name, false) => self.buttons.retain(|b| *b != name),
+    }
+  }
+
+  // releases all sticks and buttons, for a controller going away
+  // while held
+  pub fn reset(&mut self) {
+    *self = Gamepad::default();
+  }
+
+  pub fn is_down(&self, button: &str) -> bool {
+    self.buttons.iter().any(|b| b == button)
+  }
+
+  // stick position in -1..1 with the dead zone cut out, keeps direction
+  fn stick(&self, x: Axis, y: Axis, dead_zone: f32) -> (f32, f32) {
+    let x = self.axes[x as usize] as f32 / 32767.0;
+    let y = self.axes[y as usize] as f32 / 32767.0;
+
+    let dead_zone = clamp_f(dead_zone, 0.0, 0.99);
+    let len = f32::sqrt(x * x + y * y);
+    if len <= dead_zone {
+      return (0.0, 0.0);
+    }
+
+    let k = f32::min((len - dead_zone) / (1.0 - dead_zone), 1.0) / len;
+    (x * k, y * k)
+  }
+
+  // adds sticks and buttons to the input, left stick moves and
+  // right stick turns
+  pub fn apply(&self, input: &mut Input, config: &Config) {
+    let (mx, my) = self.stick(Axis::LeftX, Axis::LeftY, config.dead_zone);
+    let (tx, ty) = self.stick(Axis::RightX, Axis::RightY, config.dead_zone);
+    let ty = if config.pad_invert { -ty } else { ty };
+
+    input.forward = clamp_f(input.forward - my, -1.0, 1.0);
+    input.strafe = clamp_f(input.strafe + mx, -1.0, 1.0);
+    input.turn = clamp_f(input.turn - tx, -1.0, 1.0);
+    input.look = clamp_f(input.look - ty, -1.0, 1.0);
+
+    for b in &config.buttons {
+      if b.keys.iter().any(|k| self.is_down(k)) {
+        input.press(b.action);
+      }
+    }
+  }
+}
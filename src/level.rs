@@ -0,0 +1,398 @@
+use data::*;
+use util::*;
+use triangulate::*;
+
+use serde_json;
+
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+
+pub struct Level {
+  pub walls: Vec<Wall>,
+  pub floors: Vec<Floor>,
+  pub sprites: Vec<Sprite>,
+  pub sky: Option<Sky>,
+  pub environment: Environment,
+  pub sectors: Vec<Sector>,
+  pub movers: Vec<Mover>,
+}
+
+// a single problem found in the level, field is a path like "walls[3].tex"
+#[derive(Debug)]
+pub struct Problem {
+  pub field: String,
+  pub message: String,
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+  Io(String, io::Error),
+  Syntax(String, serde_json::Error),
+  Invalid(String, Vec<Problem>),
+}
+
+impl fmt::Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}: {}", self.field, self.message)
+  }
+}
+
+impl fmt::Display for LevelError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      LevelError::Io(ref path, ref err) => write!(f, "{}: {}", path, err),
+      LevelError::Syntax(ref path, ref err) => write!(f, "{}: {}", path, err),
+      LevelError::Invalid(ref path, ref problems) => {
+        write!(f, "{}: {} problem(s)", path, problems.len())?;
+        for p in problems {
+          write!(f, "\n  {}", p)?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+impl error::Error for LevelError {
+  fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+    match *self {
+      LevelError::Io(_, ref err) => Some(err),
+      LevelError::Syntax(_, ref err) => Some(err),
+      LevelError::Invalid(..) => None,
+    }
+  }
+}
+
+// parsed section or an empty one with the parse error recorded
+fn section<T>(name: &str, parsed: Result<T, serde_json::Error>, problems: &mut Vec<Problem>) -> T
+  where T: Default
+{
+  match parsed {
+    Ok(items) => items,
+    Err(err) => {
+      problems.push(Problem {
+        field: name.to_owned(),
+        message: err.to_string(),
+      });
+      T::default()
+    }
+  }
+}
+
+impl Level {
+  // loads and validates a level against the number of available textures
+  pub fn load(path: &str, textures: usize) -> Result<Level, LevelError> {
+    let mut data = String::new();
+    File::open(path)
+      .and_then(|mut f| f.read_to_string(&mut data))
+      .map_err(|e| LevelError::Io(path.to_owned(), e))?;
+
+    let root: serde_json::Value = serde_json::from_str(data.as_str())
+      .map_err(|e| LevelError::Syntax(path.to_owned(), e))?;
+
+    let mut problems = vec![];
+
+    let environment: Option<Environment> =
+      section("environment", serde_json::from_value(root["environment"].clone()), &mut problems);
+
+    let sectors: Option<Vec<Sector>> =
+      section("sectors", serde_json::from_value(root["sectors"].clone()), &mut problems);
+
+    let movers: Option<Vec<Mover>> =
+      section("movers", serde_json::from_value(root["movers"].clone()), &mut problems);
+
+    let mut level = Level {
+      walls: section("walls", serde_json::from_value(root["walls"].clone()), &mut problems),
+      floors: section("floors", serde_json::from_value(root["floors"].clone()), &mut problems),
+      sprites: section("sprites", serde_json::from_value(root["sprites"].clone()), &mut problems),
+      sky: section("sky", serde_json::from_value(root["sky"].clone()), &mut problems),
+      environment: environment.unwrap_or_default(),
+      sectors: sectors.unwrap_or_default(),
+      movers: movers.unwrap_or_default(),
+    };
+
+    problems.extend(level.validate(textures));
+
+    if problems.is_empty() {
+      problems.extend(level.triangulate());
+    }
+
+    if problems.is_empty() {
+      Ok(level)
+    } else {
+      Err(LevelError::Invalid(path.to_owned(), problems))
+    }
+  }
+
+  // splits floors into triangles for rendering
+  pub fn triangulate(&mut self) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    for (i, f) in self.floors.iter_mut().enumerate() {
+      let points: Vec<_> = f.points.iter().map(|p| [p.x, p.y]).collect();
+      let holes: Vec<Vec<_>> = f.holes
+        .iter()
+        .map(|h| h.iter().map(|p| [p.x, p.y]).collect())
+        .collect();
+
+      match triangulate(&points, &holes) {
+        Some(triangles) => f.triangles = triangles,
+        None => {
+          problems.push(Problem {
+            field: format!("floors[{}]", i),
+            message: "polygon can not be triangulated".to_owned(),
+          })
+        }
+      }
+    }
+
+    problems
+  }
+
+  pub fn validate(&self, textures: usize) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    {
+      let mut problem = |field: String, message: &str| {
+        problems.push(Problem {
+          field: field,
+          message: message.to_owned(),
+        })
+      };
+
+      for (i, w) in self.walls.iter().enumerate() {
+        let field = |name: &str| format!("walls[{}].{}", i, name);
+
+        if [w.x0, w.y0, w.x1, w.y1, w.z, w.h].iter().any(|v| !v.is_finite()) {
+          problem(format!("walls[{}]", i), "coordinates must be finite");
+        } else {
+          if vec_len(&[w.x0, w.y0], &[w.x1, w.y1]) == 0.0 {
+            problem(format!("walls[{}]", i), "wall has zero length");
+          }
+          if w.h <= 0.0 {
+            problem(field("h"), "height must be positive");
+          }
+        }
+        if !valid_mapping(w.u_offset, w.v_offset, w.u_scale, w.v_scale) {
+          problem(format!("walls[{}]", i), "texture mapping must be finite with non-zero scale");
+        }
+        if w.tex >= textures {
+          problem(field("tex"), "texture index out of range");
+        }
+      }
+
+      for (i, f) in self.floors.iter().enumerate() {
+        let field = |name: &str| format!("floors[{}].{}", i, name);
+
+        let outline = Some(("points".to_owned(), &f.points));
+        let holes = f.holes.iter().enumerate().map(|(j, h)| (format!("holes[{}]", j), h));
+
+        let mut simple = true;
+        for (name, points) in outline.into_iter().chain(holes) {
+          if points.iter().any(|p| !p.x.is_finite() || !p.y.is_finite()) {
+            problem(field(&name), "coordinates must be finite");
+          } else if points.len() < 3 {
+            problem(field(&name), "polygon needs at least 3 points");
+          } else if !is_simple(points) {
+            problem(field(&name), "polygon edges intersect");
+          } else {
+            continue;
+          }
+          simple = false;
+        }
+
+        // holes must lie apart inside the outline to be bridged
+        if simple {
+          for (j, h) in f.holes.iter().enumerate() {
+            let name = format!("holes[{}]", j);
+
+            if rings_cross(h, &f.points) {
+              problem(field(&name), "hole crosses the outline");
+            } else if !point_in_polygon(&[h[0].x, h[0].y], &f.points) {
+              problem(field(&name), "hole is outside the outline");
+            } else if let Some(k) = (0..j).find(|&k| rings_overlap(h, &f.holes[k])) {
+              problem(field(&name), &format!("hole overlaps holes[{}]", k));
+            }
+          }
+        }
+        if !f.height.is_finite() {
+          problem(field("height"), "height must be finite");
+        }
+        if !valid_mapping(f.u_offset, f.v_offset, f.u_scale, f.v_scale) || !f.rotation.is_finite() {
+          problem(format!("floors[{}]", i), "texture mapping must be finite with non-zero scale");
+        }
+        if f.tex >= textures {
+          problem(field("tex"), "texture index out of range");
+        }
+      }
+
+      for (i, s) in self.sprites.iter().enumerate() {
+        let field = |name: &str| format!("sprites[{}].{}", i, name);
+
+        if [s.point.x, s.point.y, s.point.z].iter().any(|v| !v.is_finite()) {
+          problem(field("point"), "coordinates must be finite");
+        }
+        if s.tex >= textures {
+          problem(field("tex"), "texture index out of range");
+        }
+      }
+
+      for (i, s) in self.sectors.iter().enumerate() {
+        let field = |name: &str| format!("sectors[{}].{}", i, name);
+
+        if s.edges.iter().any(|e| !e.x.is_finite() || !e.y.is_finite()) {
+          problem(field("edges"), "coordinates must be finite");
+        } else if s.edges.len() < 3 {
+          problem(field("edges"), "sector needs at least 3 edges");
+        } else if !is_convex_ccw(s) {
+          problem(field("edges"), "sector must be convex and counterclockwise");
+        }
+        if !s.floor.is_finite() || !s.ceiling.is_finite() || s.floor >= s.ceiling {
+          problem(format!("sectors[{}]", i), "floor must be below ceiling");
+        }
+        for (name, tex) in [("floor_tex", s.floor_tex), ("ceiling_tex", s.ceiling_tex), ("wall_tex", s.wall_tex)] {
+          if tex >= textures {
+            problem(field(name), "texture index out of range");
+          }
+        }
+        for (j, e) in s.edges.iter().enumerate() {
+          if let Some(p) = e.portal {
+            let portal = field(&format!("edges[{}].portal", j));
+            if p >= self.sectors.len() {
+              problem(portal, "portal to a missing sector");
+            } else if p == i {
+              problem(portal, "portal back into its own sector");
+            }
+          }
+          if e.tex.is_some_and(|tex| tex >= textures) {
+            problem(field(&format!("edges[{}].tex", j)), "texture index out of range");
+          }
+        }
+      }
+
+      for (i, m) in self.movers.iter().enumerate() {
+        let field = |name: &str| format!("movers[{}].{}", i, name);
+
+        let targets = match m.kind {
+          MoverKind::Door | MoverKind::Slide => self.walls.len(),
+          MoverKind::Lift => self.floors.len(),
+        };
+        if m.target >= targets {
+          problem(field("target"), "target index out of range");
+        } else if m.kind == MoverKind::Door && m.distance >= self.walls[m.target].h {
+          problem(field("distance"), "door must open less than its height");
+        }
+        let door = m.kind == MoverKind::Door;
+        if !m.distance.is_finite() || m.distance == 0.0 || (door && m.distance < 0.0) {
+          problem(field("distance"), "distance must be finite and non-zero, positive for doors");
+        }
+        if !m.speed.is_finite() || m.speed <= 0.0 {
+          problem(field("speed"), "speed must be positive");
+        }
+        if !m.wait.is_finite() {
+          problem(field("wait"), "wait must be finite");
+        }
+
+        // walls and floors are indexed separately
+        let lift = m.kind == MoverKind::Lift;
+        let shared = self.movers[..i]
+          .iter()
+          .position(|o| o.target == m.target && (o.kind == MoverKind::Lift) == lift);
+        if let Some(j) = shared {
+          problem(field("target"), &format!("target is already moved by movers[{}]", j));
+        }
+      }
+
+      if self.environment.ground && self.environment.ground_tex >= textures {
+        problem("environment.ground_tex".to_owned(), "texture index out of range");
+      }
+
+      if let Some(ref sky) = self.sky {
+        if sky.tex.is_some_and(|tex| tex >= textures) {
+          problem("sky.tex".to_owned(), "texture index out of range");
+        }
+        if let Some(ref clouds) = sky.clouds {
+          if clouds.tex >= textures {
+            problem("sky.clouds.tex".to_owned(), "texture index out of range");
+          }
+          if !clouds.speed.is_finite() || !clouds.opacity.is_finite() {
+            problem("sky.clouds".to_owned(), "speed and opacity must be finite");
+          }
+        }
+      }
+    }
+
+    problems
+  }
+}
+
+fn valid_mapping(u_offset: f32, v_offset: f32, u_scale: f32, v_scale: f32) -> bool {
+  [u_offset, v_offset, u_scale, v_scale].iter().all(|v| v.is_finite()) && u_scale != 0.0 &&
+  v_scale != 0.0
+}
+
+// no corner turns right, collinear edges are fine as
+// long walls are split for portals
+fn is_convex_ccw(s: &Sector) -> bool {
+  let n = s.edges.len();
+  let turns = (0..n).all(|i| {
+    let (a, b) = s.edge(i);
+    let (_, c) = s.edge((i + 1) % n);
+    vec_cross(&vec_sub(&b, &a), &vec_sub(&c, &b)) >= 0.0
+  });
+
+  let area = (0..n).fold(0.0, |area, i| {
+    let (a, b) = s.edge(i);
+    area + vec_cross(&a, &b)
+  });
+
+  turns && area > 0.0
+}
+
+// some edges of the two polygons touch or intersect
+fn rings_cross(a: &[Point2], b: &[Point2]) -> bool {
+  let edge = |points: &[Point2], i: usize| {
+    let j = (i + 1) % points.len();
+    ([points[i].x, points[i].y], [points[j].x, points[j].y])
+  };
+
+  (0..a.len()).any(|i| {
+    let (p1, p2) = edge(a, i);
+    (0..b.len()).any(|j| {
+      let (p3, p4) = edge(b, j);
+      segments_intersect(&p1, &p2, &p3, &p4)
+    })
+  })
+}
+
+// polygons cross or one is inside the other
+fn rings_overlap(a: &[Point2], b: &[Point2]) -> bool {
+  rings_cross(a, b) || point_in_polygon(&[a[0].x, a[0].y], b) ||
+  point_in_polygon(&[b[0].x, b[0].y], a)
+}
+
+// polygon has no intersecting non-adjacent edges
+fn is_simple(points: &[Point2]) -> bool {
+  let n = points.len();
+  let edge = |i: usize| ([points[i].x, points[i].y], [points[(i + 1) % n].x, points[(i + 1) % n].y]);
+
+  for i in 0..n {
+    for j in i + 1..n {
+      // adjacent edges share a point
+      if j == i + 1 || (i == 0 && j == n - 1) {
+        continue;
+      }
+
+      let (a, b) = edge(i);
+      let (c, d) = edge(j);
+      if segments_intersect(&a, &b, &c, &d) {
+        return false;
+      }
+    }
+  }
+
+  true
+}
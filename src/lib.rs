@@ -0,0 +1,18 @@
+extern crate serde_json;
+#[macro_use]
+extern crate serde_derive;
+
+pub mod game;
+pub mod render;
+pub mod data;
+pub mod buffer;
+pub mod util;
+pub mod collision;
+pub mod headless;
+pub mod level;
+pub mod texture;
+pub mod triangulate;
+pub mod bsp;
+pub mod input;
+pub mod replay;
+pub mod mover;
@@ -1,8 +1,7 @@
 extern crate sdl2;
-extern crate serde_json;
-#[macro_use]
-extern crate serde_derive;
+extern crate raster;
 
+use std::io;
 use std::path::Path;
 
 use sdl2::pixels::PixelFormatEnum;
@@ -12,105 +11,361 @@ use sdl2::image::LoadSurface;
 use sdl2::keyboard::Scancode;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
+use sdl2::video::FullscreenType;
+use sdl2::controller;
+use sdl2::controller::GameController;
 
-mod game;
-mod render;
-mod data;
-mod buffer;
-mod util;
+use raster::game::*;
+use raster::data::*;
+use raster::headless;
+use raster::input::*;
+use raster::replay::*;
+use raster::texture::*;
+use raster::util::*;
 
-use game::*;
+// longest frame time simulated, in seconds
+const MAX_FRAME_TIME: f64 = 0.25;
+
+// texture images in the order levels index them
+const TEXTURE_FILES: [&str; 4] = ["../data/floor.png", "../data/wall.png", "../data/sprite.png", "../data/floor2.png"];
+
+// internal resolutions, switched with - and =
+const RESOLUTIONS: [(usize, usize); 5] = [(160, 120), (200, 150), (320, 240), (400, 300), (640, 480)];
 
 pub fn main() {
+  // raster --no-vsync renders as fast as possible
+  let vsync = !std::env::args().any(|a| a == "--no-vsync");
+  let mut args: Vec<String> = std::env::args().filter(|a| a != "--no-vsync").collect();
+
+  // raster --record out.json saves the input of every update
+  let record = match args.iter().position(|a| a == "--record") {
+    Some(i) if i + 1 < args.len() => {
+      let path = args.remove(i + 1);
+      args.remove(i);
+      Some(path)
+    }
+    _ => None,
+  };
+
+  // raster [level.json]
+  let level = if args.len() == 2 { args[1].clone() } else { "../data/map.json".to_owned() };
+
+  // raster --render x y z dir out.ppm
+  if args.len() == 7 && args[1] == "--render" {
+    let f: Vec<f32> = args[2..6].iter().map(|a| a.parse().unwrap()).collect();
+    render_to_file(Point3 { x: f[0], y: f[1], z: f[2] }, f[3], Path::new(&args[6]));
+    return;
+  }
+
+  // raster --replay in.json [frames_dir]
+  if (args.len() == 3 || args.len() == 4) && args[1] == "--replay" {
+    replay_file(&args[2], args.get(3).map(Path::new));
+    return;
+  }
+
+  // the headless modes above only load images and never initialize sdl
   let sdl_context = sdl2::init().unwrap();
 
   let video_subsystem = sdl_context.video().unwrap();
-  let mut timer = sdl_context.timer().unwrap();
+  let timer = sdl_context.timer().unwrap();
 
   let window = video_subsystem.window("Raster", 800, 600)
         .position_centered()
+        .resizable()
        // .opengl()
         .build()
         .unwrap();
 
-  let mut renderer = window.renderer()
-    .accelerated()
-    .build()
-    .unwrap();
+  let mut renderer = if vsync {
+    window.renderer().accelerated().present_vsync().build().unwrap()
+  } else {
+    window.renderer().accelerated().build().unwrap()
+  };
 
-  let mut texture = renderer.create_texture_streaming(PixelFormatEnum::RGB24, 256, 256).unwrap();
+  let controller_subsystem = sdl_context.game_controller().unwrap();
+  let mut controllers: Vec<GameController> = vec![];
+  let mut gamepad = Gamepad::default();
 
   let mut event_pump = sdl_context.event_pump().unwrap();
 
-  let surfaces = vec![Surface::from_file(Path::new("../data/floor.png")).unwrap(),
-                      Surface::from_file(Path::new("../data/wall.png")).unwrap(),
-                      Surface::from_file(Path::new("../data/sprite.png")).unwrap(),
-                      Surface::from_file(Path::new("../data/floor2.png")).unwrap()];
+  // hide the cursor and report motion past the window edges
+  sdl_context.mouse().set_relative_mouse_mode(true);
+
+  let surfaces = load_surfaces();
+
+  let textures = surface_textures(&surfaces);
+
+  // key bindings, defaults when there is no config
+  let config = match Config::load("../data/config.json") {
+    Ok(config) => config,
+    Err(ref e) if e.kind() == io::ErrorKind::NotFound => Config::default(),
+    Err(e) => {
+      println!("../data/config.json: {}", e);
+      return;
+    }
+  };
+
+  for b in &config.bindings {
+    for k in b.keys.iter().filter(|k| Scancode::from_name(k).is_none()) {
+      println!("unknown key \"{}\" bound to {:?}", k, b.action);
+    }
+  }
+
+  let mut resolution = 1;
+  let (width, height) = RESOLUTIONS[resolution];
 
-  let mut textures = vec![];
-  for s in &surfaces {
-    textures.push(s.without_lock().unwrap());
+  let mut game = match Game::new(width, height, textures, level.clone()) {
+    Ok(game) => game,
+    Err(e) => {
+      println!("{}", e);
+      return;
+    }
+  };
+
+  let fov = game.renderer.set_fov(config.fov);
+  if fov != config.fov {
+    println!("fov {} out of range, using {}", config.fov, fov);
   }
 
-  let mut game = Game::new(200, 150, textures, "../data/map.json".to_owned());
+  // one column strip per core
+  let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+  game.renderer.set_threads(cores);
 
-  let mut prev_t = timer.ticks();
-  let mut fps_t = 0i64;
+  let mut texture = renderer.create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+    .unwrap();
+
+  let mut recording = record.as_ref().map(|_| Recording::new(&level, TICK));
+
+  let mut fullscreen = false;
+  let mut integer_scale = false;
+
+  let frequency = timer.performance_frequency() as f64;
+  let mut prev_t = timer.performance_counter();
+  let mut fps_t = 0.0;
   let mut frames = 0;
 
+  // simulation time not yet run by ticks
+  let mut accumulator = 0.0;
+
   'running: loop {
-    let now = timer.ticks();
-    let dt: i64 = now as i64 - prev_t as i64;
-    let sec = dt as f64 / 1_000.0;
-    let fps_cap = 30;
-    if dt < 1_000 / fps_cap {
-      timer.delay((1_000 / fps_cap - dt) as u32);
-      continue;
-    }
+    let now = timer.performance_counter();
+    let sec = (now - prev_t) as f64 / frequency;
     prev_t = now;
 
-    fps_t += dt;
+    // don't try to catch up after a long stall
+    accumulator += f64::min(sec, MAX_FRAME_TIME);
+
+    fps_t += sec;
     frames += 1;
-    
-    if fps_t > 1000 {
-      println!("FPS = {}", frames);
-      fps_t -= 1000;
+
+    if fps_t > 1.0 {
+      println!("FPS = {}, drawn = {}, culled = {}",
+               frames,
+               game.renderer.stats.drawn,
+               game.renderer.stats.culled);
+      fps_t -= 1.0;
       frames = 0;
     }
 
+    let mut new_resolution = resolution;
+    let mut mouse = (0, 0);
+
     for event in event_pump.poll_iter() {
       match event {
         Event::Quit { .. } |
         Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'running,
+        Event::KeyDown { keycode: Some(Keycode::Minus), .. } => {
+          new_resolution = new_resolution.saturating_sub(1);
+        }
+        Event::KeyDown { keycode: Some(Keycode::Equals), .. } => {
+          new_resolution = std::cmp::min(new_resolution + 1, RESOLUTIONS.len() - 1);
+        }
+        Event::KeyDown { keycode: Some(Keycode::I), .. } => integer_scale = !integer_scale,
+        Event::MouseMotion { xrel, yrel, .. } => mouse = (mouse.0 + xrel, mouse.1 + yrel),
+        // also sent for controllers connected at startup
+        Event::ControllerDeviceAdded { which, .. } => {
+          match controller_subsystem.open(which as u32) {
+            Ok(c) => controllers.push(c),
+            Err(e) => println!("controller {}: {}", which, e),
+          }
+        }
+        Event::ControllerDeviceRemoved { which, .. } => {
+          controllers.retain(|c| c.instance_id() != which);
+          // input is shared by all pads, only released with the last one
+          if controllers.is_empty() {
+            gamepad.reset();
+          }
+        }
+        Event::ControllerAxisMotion { axis, value, .. } => {
+          let axis = match axis {
+            controller::Axis::LeftX => Some(Axis::LeftX),
+            controller::Axis::LeftY => Some(Axis::LeftY),
+            controller::Axis::RightX => Some(Axis::RightX),
+            controller::Axis::RightY => Some(Axis::RightY),
+            _ => None,
+          };
+          if let Some(axis) = axis {
+            gamepad.event(PadEvent::Axis(axis, value));
+          }
+        }
+        Event::ControllerButtonDown { button, .. } => gamepad.event(PadEvent::Button(button.string(), true)),
+        Event::ControllerButtonUp { button, .. } => gamepad.event(PadEvent::Button(button.string(), false)),
+        Event::KeyDown { keycode: Some(Keycode::F11), .. } => {
+          fullscreen = !fullscreen;
+          let mode = if fullscreen { FullscreenType::Desktop } else { FullscreenType::Off };
+          renderer.window_mut().unwrap().set_fullscreen(mode).unwrap();
+        }
         _ => {}
       }
     }
 
-    game.in_fwd = event_pump.keyboard_state().is_scancode_pressed(Scancode::W);
-    game.in_back = event_pump.keyboard_state().is_scancode_pressed(Scancode::S);
-    game.in_left = event_pump.keyboard_state().is_scancode_pressed(Scancode::A);
-    game.in_right = event_pump.keyboard_state().is_scancode_pressed(Scancode::D);
+    if new_resolution != resolution {
+      resolution = new_resolution;
+      let (width, height) = RESOLUTIONS[resolution];
+      println!("resolution {}x{}", width, height);
 
-    game.in_turn_left = event_pump.keyboard_state().is_scancode_pressed(Scancode::Left);
-    game.in_turn_right = event_pump.keyboard_state().is_scancode_pressed(Scancode::Right);
+      game.renderer.resize(width, height);
+      texture = renderer.create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+        .unwrap();
+    }
 
-    game.update(sec);
-    game.draw();
+    let mut input = {
+      let keys = event_pump.keyboard_state();
+      config.input(|k| Scancode::from_name(k).is_some_and(|s| keys.is_scancode_pressed(s)))
+    };
+    input.mouse_motion(mouse.0, mouse.1, &config);
+    gamepad.apply(&mut input, &config);
+    game.set_input(input);
 
-    texture.update(Some(Rect::new(0,
-                                  0,
-                                  game.renderer.width as u32,
-                                  game.renderer.height as u32)),
-                   &game.get_data(),
-                   256 * 3)
+    while accumulator >= TICK {
+      if let Some(ref mut recording) = recording {
+        recording.record(&game);
+      }
+      game.update(TICK);
+      accumulator -= TICK;
+    }
+
+    // pose between the last two ticks
+    game.draw_interpolated((accumulator / TICK) as f32);
+
+    texture.update(None, &game.get_data(), game.renderer.width * 3)
       .unwrap();
+
+    // letterboxed to the window keeping the aspect
+    let (window_width, window_height) = renderer.output_size().unwrap();
+    let (x, y, w, h) = fit_rect((game.renderer.width, game.renderer.height),
+                                (window_width as usize, window_height as usize),
+                                integer_scale);
+
+    renderer.clear();
     renderer.copy(&texture,
-                  Some(Rect::new(0,
-                                 0,
-                                 game.renderer.width as u32,
-                                 game.renderer.height as u32)),
-                  Some(Rect::new(0, 0, 800, 600)))
+                  None,
+                  Some(Rect::new(x as i32, y as i32, w as u32, h as u32)))
       .unwrap();
     renderer.present();
   }
+
+  if let (Some(path), Some(recording)) = (record, recording) {
+    if let Err(e) = recording.save(&path) {
+      println!("{}: {}", path, e);
+    }
+  }
+}
+
+fn load_surfaces() -> Vec<Surface<'static>> {
+  TEXTURE_FILES.iter().map(|f| Surface::from_file(Path::new(f)).unwrap()).collect()
+}
+
+// exits when an image has a pixel format textures can't use
+fn surface_textures<'a>(surfaces: &'a [Surface<'static>]) -> Vec<Texture<'a>> {
+  surfaces.iter()
+    .zip(TEXTURE_FILES.iter())
+    .map(|(s, f)| match surface_texture(s) {
+      Ok(texture) => texture,
+      Err(e) => {
+        println!("{}: {}", f, e);
+        std::process::exit(1);
+      }
+    })
+    .collect()
+}
+
+fn surface_texture<'a>(s: &'a Surface) -> Result<Texture<'a>, String> {
+  let format = match s.pixel_format_enum() {
+    PixelFormatEnum::RGB24 => Format::Rgb24,
+    // R, G, B, A byte order on little endian
+    PixelFormatEnum::ABGR8888 => Format::Rgba32,
+    PixelFormatEnum::Index8 => {
+      let palette = unsafe {
+        let palette = (*(*s.raw()).format).palette;
+        if palette.is_null() {
+          return Err("indexed surface has no palette".to_owned());
+        }
+        let colors = std::slice::from_raw_parts((*palette).colors, (*palette).ncolors as usize);
+        colors.iter().map(|c| [c.r, c.g, c.b]).collect()
+      };
+      Format::Indexed(palette)
+    }
+    f => return Err(format!("unsupported texture format {:?}", f)),
+  };
+
+  let pixels = s.without_lock().ok_or("surface must be locked".to_owned())?;
+
+  Texture::new(pixels,
+               s.width() as usize,
+               s.height() as usize,
+               s.pitch() as usize,
+               format)
+}
+
+fn render_to_file(point: Point3, dir: f32, path: &Path) {
+  let surfaces = load_surfaces();
+
+  let textures = surface_textures(&surfaces);
+
+  let mut game = match Game::new(200, 150, textures, "../data/map.json".to_owned()) {
+    Ok(game) => game,
+    Err(e) => {
+      println!("{}", e);
+      return;
+    }
+  };
+  let pixels = headless::render_frame(&mut game, point, dir);
+
+  headless::write_ppm(path, game.renderer.width, game.renderer.height, &pixels).unwrap();
+}
+
+// runs a recording without a window, prints the final pose and
+// optionally writes every frame
+fn replay_file(path: &str, frames: Option<&Path>) {
+  let recording = match Recording::load(path) {
+    Ok(recording) => recording,
+    Err(e) => {
+      println!("{}: {}", path, e);
+      return;
+    }
+  };
+
+  let surfaces = load_surfaces();
+
+  let textures = surface_textures(&surfaces);
+
+  let mut game = match Game::new(200, 150, textures, recording.level.clone()) {
+    Ok(game) => game,
+    Err(e) => {
+      println!("{}", e);
+      return;
+    }
+  };
+
+  recording.replay(&mut game, |i, game| {
+    if let Some(dir) = frames {
+      let pixels = headless::current_frame(game);
+      let out = dir.join(format!("frame{:05}.ppm", i));
+      headless::write_ppm(&out, game.renderer.width, game.renderer.height, &pixels).unwrap();
+    }
+  });
+
+  let p = &game.player;
+  println!("{} {} {} {}", p.point.x, p.point.y, p.point.z, p.dir);
 }
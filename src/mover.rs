@@ -0,0 +1,121 @@
+use data::*;
+use bsp::*;
+use util::*;
+
+// how far the action key reaches
+pub const USE_RANGE: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Phase {
+  Closed,
+  Opening,
+  // seconds left before closing
+  Open(f32),
+  Closing,
+}
+
+// geometry the mover starts from
+enum Base {
+  Wall(Wall),
+  Floor(f32),
+}
+
+pub struct MoverState {
+  pub phase: Phase,
+  // travel from the closed position, up to the mover distance
+  pub pos: f32,
+  base: Base,
+}
+
+impl MoverState {
+  pub fn new(m: &Mover, walls: &[Wall], floors: &[Floor]) -> MoverState {
+    let base = match m.kind {
+      MoverKind::Door | MoverKind::Slide => Base::Wall(walls[m.target].clone()),
+      MoverKind::Lift => Base::Floor(floors[m.target].height),
+    };
+
+    MoverState {
+      phase: Phase::Closed,
+      pos: 0.0,
+      base: base,
+    }
+  }
+
+  pub fn activate(&mut self) {
+    self.phase = match self.phase {
+      Phase::Closed | Phase::Closing => Phase::Opening,
+      Phase::Open(_) => Phase::Closing,
+      Phase::Opening => Phase::Opening,
+    };
+  }
+
+  // advances the movement, true if the geometry has to be moved
+  pub fn update(&mut self, m: &Mover, dt: f32) -> bool {
+    let travel = m.distance.abs();
+
+    match self.phase {
+      Phase::Closed => false,
+      Phase::Opening => {
+        self.pos = f32::min(self.pos + m.speed * dt, travel);
+        if self.pos == travel {
+          self.phase = Phase::Open(m.wait);
+        }
+        true
+      }
+      Phase::Open(left) => {
+        if m.wait >= 0.0 {
+          let left = left - dt;
+          self.phase = if left <= 0.0 { Phase::Closing } else { Phase::Open(left) };
+        }
+        false
+      }
+      Phase::Closing => {
+        self.pos = f32::max(self.pos - m.speed * dt, 0.0);
+        if self.pos == 0.0 {
+          self.phase = Phase::Closed;
+        }
+        true
+      }
+    }
+  }
+
+  // moves the target to the current position
+  pub fn apply(&self, m: &Mover, walls: &mut [Wall], floors: &mut [Floor], bsp: &mut BspTree) {
+    let offset = self.pos * m.distance.signum();
+
+    match self.base {
+      Base::Wall(ref base) if m.kind == MoverKind::Door => {
+        let w = &mut walls[m.target];
+        w.z = base.z + offset;
+        w.h = base.h - offset;
+        bsp.set_height(m.target, w.z, w.h);
+      }
+      Base::Wall(ref base) => {
+        let len = vec_len(&[base.x0, base.y0], &[base.x1, base.y1]);
+        let (dx, dy) = ((base.x1 - base.x0) / len * offset, (base.y1 - base.y0) / len * offset);
+
+        let w = &mut walls[m.target];
+        w.x0 = base.x0 + dx;
+        w.y0 = base.y0 + dy;
+        w.x1 = base.x1 + dx;
+        w.y1 = base.y1 + dy;
+      }
+      Base::Floor(height) => floors[m.target].height = height + offset,
+    }
+  }
+}
+
+// distance along the ray from p in direction d to the segment
+pub fn ray_hit(p: &[f32; 2], d: &[f32; 2], a: &[f32; 2], b: &[f32; 2]) -> Option<f32> {
+  let ab = vec_sub(b, a);
+  let denom = vec_cross(d, &ab);
+  if denom == 0.0 {
+    return None;
+  }
+
+  let ap = vec_sub(a, p);
+  let t = vec_cross(&ap, &ab) / denom;
+  let s = vec_cross(&ap, d) / denom;
+
+  if t >= 0.0 && (0.0..=1.0).contains(&s) { Some(t) } else { None }
+}
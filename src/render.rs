@@ -1,27 +1,186 @@
 use data::*;
+use bsp::*;
 use buffer::*;
+use texture::*;
 use util::*;
 
+use std;
 use std::cmp;
 use std::mem;
+use std::thread;
+
+// texture repeats per world unit
+const TEX_REPEAT: f32 = 2.0;
+
+// max number of portals passed from the player sector
+const MAX_PORTAL_DEPTH: usize = 32;
+// sector walls are clipped closer than everything else, the
+// player can come right up to a portal
+const SECTOR_NEAR: f32 = 0.001;
+
+// rows still open in every screen column, walls drawn front to
+// back close them from the top and bottom
+pub struct Coverage {
+  top: Vec<isize>,
+  bottom: Vec<isize>,
+  full: usize,
+}
+
+impl Coverage {
+  // columns outside the drawn ones start closed
+  pub fn new(columns: (usize, usize), width: usize, height: usize) -> Coverage {
+    let mut coverage = Coverage {
+      top: vec![0; width],
+      bottom: vec![height as isize - 1; width],
+      full: 0,
+    };
+
+    for x in (0..columns.0).chain(columns.1..width) {
+      coverage.cover(x, 0, height as isize);
+    }
+
+    coverage
+  }
+
+  pub fn is_full(&self) -> bool {
+    self.full == self.top.len()
+  }
+
+  fn is_column_full(&self, x: usize) -> bool {
+    self.top[x] > self.bottom[x]
+  }
+
+  // marks rows y1..y2 of the column as covered, only spans touching
+  // the open part are tracked
+  fn cover(&mut self, x: usize, y1: isize, y2: isize) {
+    if self.is_column_full(x) {
+      return;
+    }
+
+    if y1 <= self.top[x] && y2 > self.top[x] {
+      self.top[x] = y2;
+    }
+    if y2 > self.bottom[x] && y1 <= self.bottom[x] {
+      self.bottom[x] = y1 - 1;
+    }
+
+    if self.is_column_full(x) {
+      self.full += 1;
+    }
+  }
+}
+
+// primitives drawn and culled during the last frame
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct FrameStats {
+  pub drawn: usize,
+  pub culled: usize,
+}
+
+// texture mapping of a horizontal plane
+struct PlaneMapping {
+  height: f32,
+  tex: usize,
+  tint: [u8; 3],
+  // radians
+  rotation: f32,
+  offset: (f32, f32),
+  scale: (f32, f32),
+}
+
+impl PlaneMapping {
+  // texture aligned to the world axes
+  fn plain(height: f32, tex: usize, tint: [u8; 3]) -> PlaneMapping {
+    PlaneMapping {
+      height: height,
+      tex: tex,
+      tint: tint,
+      rotation: 0.0,
+      offset: (0.0, 0.0),
+      scale: (1.0, 1.0),
+    }
+  }
+
+  fn ground(env: &Environment) -> PlaneMapping {
+    PlaneMapping::plain(0.0, env.ground_tex, env.ground_tint)
+  }
+
+  fn floor(floor: &Floor) -> PlaneMapping {
+    PlaneMapping {
+      height: floor.height,
+      tex: floor.tex,
+      tint: floor.color,
+      rotation: floor.rotation.to_radians(),
+      offset: (floor.u_offset, floor.v_offset),
+      scale: (floor.u_scale, floor.v_scale),
+    }
+  }
+}
+
+// screen columns a sector is drawn into and the rows still open in
+// each column, inclusive
+struct Window<'c> {
+  columns: (isize, isize),
+  ytop: &'c mut [isize],
+  ybottom: &'c mut [isize],
+}
+
+// one screen column of a sector wall
+struct WallColumn {
+  x: isize,
+  vz: f32,
+  tx: f32,
+  // screen y and world height of the top and bottom
+  yc: f32,
+  yf: f32,
+  ceiling: f32,
+  floor: f32,
+  tex: usize,
+  tint: [u8; 3],
+}
+
+// everything drawn in a frame
+pub struct Scene<'b> {
+  pub sectors: &'b [Sector],
+  // sector the player is in
+  pub sector: Option<usize>,
+  pub environment: &'b Environment,
+  pub bsp: &'b BspTree,
+  // walls left out of the bsp
+  pub walls: &'b [Wall],
+  pub sliding: &'b [usize],
+  pub sprites: &'b [Sprite],
+  pub floors: &'b [Floor],
+  pub sky: Option<&'b Sky>,
+}
 
 pub struct Renderer<'a> {
-  pub textures: Vec<&'a [u8]>,
+  pub textures: Vec<Texture<'a>>,
   pub buffer: Buffer,
   pub width: usize,
   pub height: usize,
   pub t: f32,
   pub ambient: [u8; 3],
+  pub stats: FrameStats,
+  // floors and ground are drawn by rows of constant depth
+  pub spans: bool,
 
   viewport: (f32, f32),
   p_z: f32,
+
+  // screen columns drawn by this renderer
+  columns: (usize, usize),
+  // renderers of column strips, each drawn by its own thread
+  strips: Vec<Renderer<'a>>,
 }
 
 impl<'a> Renderer<'a> {
-  pub fn new(width: usize, height: usize, textures: Vec<&[u8]>) -> Renderer {
-    let width_pow2 = 1 << f32::log2(width as f32).ceil() as usize;
-    let height_pow2 = 1 << f32::log2(height as f32).ceil() as usize;
+  pub fn new(width: usize, height: usize, textures: Vec<Texture<'a>>) -> Renderer<'a> {
+    Renderer::strip(width, height, textures, (0, width))
+  }
 
+  // renderer of the screen columns x0..x1 only
+  fn strip(width: usize, height: usize, textures: Vec<Texture<'a>>, columns: (usize, usize)) -> Renderer<'a> {
     let view_width = 0.2;
 
     Renderer {
@@ -29,17 +188,139 @@ impl<'a> Renderer<'a> {
       height: height,
       textures: textures,
       viewport: (view_width, (height as f32) / (width as f32) * view_width),
-      ambient: [00, 40, 00],
+      ambient: Environment::default().ambient,
+      stats: FrameStats::default(),
+      spans: true,
       p_z: 0.1,
       t: 0.0,
-      buffer: Buffer::new(width_pow2, height_pow2),
+      buffer: Buffer::strip(columns.0, columns.1 - columns.0, height),
+      columns: columns,
+      strips: vec![],
+    }
+  }
+
+  // horizontal field of view in degrees
+  pub fn fov(&self) -> f32 {
+    (2.0 * f32::atan(self.viewport.0 / 2.0 / self.p_z)).to_degrees()
+  }
+
+  // clamped to 10..170 degrees, returns the field of view used
+  pub fn set_fov(&mut self, degrees: f32) -> f32 {
+    let degrees = clamp_f(degrees, 10.0, 170.0);
+
+    self.viewport.0 = 2.0 * self.p_z * f32::tan(degrees.to_radians() / 2.0);
+    self.viewport.1 = (self.height as f32) / (self.width as f32) * self.viewport.0;
+    degrees
+  }
+
+  // distance of the horizon from the top of the viewport, moved
+  // down when looking up
+  fn horizon(&self, player: &Player) -> f32 {
+    self.viewport.1 / 2.0 + self.p_z * f32::tan(player.pitch)
+  }
+
+  // changes the internal resolution, keeps the horizontal field of view
+  pub fn resize(&mut self, width: usize, height: usize) {
+    let threads = self.threads();
+
+    self.width = width;
+    self.height = height;
+    self.viewport.1 = (height as f32) / (width as f32) * self.viewport.0;
+    self.buffer = Buffer::new(width, height);
+    self.columns = (0, width);
+
+    self.set_threads(threads);
+  }
+
+  // splits the screen into column strips drawn in parallel, the
+  // image is the same for any number of threads
+  pub fn set_threads(&mut self, threads: usize) {
+    let n = clamp(threads as isize, 1, self.width as isize) as usize;
+
+    self.strips.clear();
+    if n == 1 {
+      return;
+    }
+
+    for i in 0..n {
+      let x0 = self.width * i / n;
+      let x1 = self.width * (i + 1) / n;
+
+      let strip = Renderer::strip(self.width, self.height, self.textures.clone(), (x0, x1));
+      self.strips.push(strip);
+    }
+  }
+
+  pub fn threads(&self) -> usize {
+    cmp::max(self.strips.len(), 1)
+  }
+
+  pub fn draw_scene(&mut self, scene: &Scene, player: &Player) {
+    if self.strips.is_empty() {
+      self.draw_columns(scene, player);
+      return;
+    }
+
+    for strip in &mut self.strips {
+      strip.t = self.t;
+      strip.ambient = self.ambient;
+      strip.spans = self.spans;
+      strip.viewport = self.viewport;
+    }
+
+    thread::scope(|s| {
+      for strip in &mut self.strips {
+        s.spawn(move || strip.draw_columns(scene, player));
+      }
+    });
+
+    // strips see the same primitives in the same order, they only
+    // stop the bsp walk once their own columns are covered, so the
+    // strip drawing the most counts like the whole screen would
+    self.stats = FrameStats::default();
+    for strip in &self.strips {
+      self.buffer.copy_strip(&strip.buffer);
+      if strip.stats.drawn >= self.stats.drawn {
+        self.stats = strip.stats;
+      }
+    }
+  }
+
+  fn draw_columns(&mut self, scene: &Scene, player: &Player) {
+    self.buffer.clear();
+    self.stats = FrameStats::default();
+
+    // sectors cover the whole screen when we are inside one
+    match scene.sector {
+      Some(i) => self.draw_sectors(scene.sectors, i, player),
+      None => self.draw_background(scene.environment, player),
+    }
+
+    self.draw_bsp(scene.bsp, player);
+
+    for &i in scene.sliding {
+      self.draw_wall(&scene.walls[i], player);
+    }
+
+    for s in scene.sprites {
+      self.draw_sprite(s, player);
+    }
+
+    for f in scene.floors {
+      self.draw_floor(f, player);
+    }
+
+    if let Some(sky) = scene.sky {
+      self.draw_sky(sky, player);
     }
   }
 
+  // near is the distance of the clipping plane
   fn project_segment(&self,
                      p1: &Point3,
                      p2: &Point3,
-                     player: &Player)
+                     player: &Player,
+                     near: f32)
                      -> Option<((isize, f32, f32, f32), (isize, f32, f32, f32))> {
     let len = vec_len(&[p1.x, p1.y], &[p2.x, p2.y]);
 
@@ -64,19 +345,26 @@ impl<'a> Renderer<'a> {
     }
 
     // both points are clipped
-    if vz1 < self.p_z && vz2 < self.p_z {
+    if vz1 < near && vz2 < near {
       return None;
     }
 
     // 1st point is clipped
-    if vz1 < self.p_z {
-      vx1 = vx2 - (vx2 - vx1) * (vz2 - self.p_z) / (vz2 - vz1);
-      vz1 = self.p_z;
+    if vz1 < near {
+      vx1 = vx2 - (vx2 - vx1) * (vz2 - near) / (vz2 - vz1);
+      vz1 = near;
 
       let new_len = vec_len(&[vx1, vz1], &[vx2, vz2]);
       tx1 = if tx1 > 0.0 { new_len } else { len - new_len };
     }
 
+    // both points are outside the left or the right plane
+    let k = self.viewport.0 / 2.0 / self.p_z;
+    if (vx1 + k * vz1 < 0.0 && vx2 + k * vz2 < 0.0) ||
+       (k * vz1 - vx1 < 0.0 && k * vz2 - vx2 < 0.0) {
+      return None;
+    }
+
     // projection consts
     let a = self.p_z;
     let b = self.viewport.0 / 2.0;
@@ -95,6 +383,33 @@ impl<'a> Renderer<'a> {
   }
 
   pub fn draw_wall(&mut self, w: &Wall, player: &Player) {
+    self.draw_wall_masked(w, player, None);
+  }
+
+  // draws walls nearest first, stops once every column is covered
+  pub fn draw_bsp(&mut self, tree: &BspTree, player: &Player) {
+    let mut coverage = Coverage::new(self.columns, self.width, self.height);
+    let mut visited = 0;
+
+    tree.walk(&[player.point.x, player.point.y], &mut |w| {
+      visited += 1;
+      self.draw_wall_masked(w, player, Some(&mut coverage));
+      !coverage.is_full()
+    });
+
+    // walls behind a full screen are culled too
+    self.stats.culled += tree.walls.len() - visited;
+  }
+
+  fn draw_wall_masked(&mut self, w: &Wall, player: &Player, mut coverage: Option<&mut Coverage>) {
+    // back face of a one-sided wall
+    let d = [w.x1 - w.x0, w.y1 - w.y0];
+    let v = [player.point.x - w.x0, player.point.y - w.y0];
+    if !w.two_sided && vec_cross(&d, &v) <= 0.0 {
+      self.stats.culled += 1;
+      return;
+    }
+
     let p1 = Point3 {
       x: w.x0,
       y: w.y0,
@@ -106,24 +421,33 @@ impl<'a> Renderer<'a> {
       z: w.z,
     };
 
-    let p = self.project_segment(&p1, &p2, player);
+    let p = self.project_segment(&p1, &p2, player, self.p_z);
 
     let (p1, p2) = match p {
       Some((p1, p2)) => (p1, p2),
-      None => return,
+      None => {
+        self.stats.culled += 1;
+        return;
+      }
     };
 
     let (p1, p2) = if p1.0 < p2.0 { (p1, p2) } else { (p2, p1) };
     let (sx1, _, vz1, tx1) = p1;
     let (sx2, _, vz2, tx2) = p2;
 
-    let xmin = cmp::max(sx1, 0);
-    let xmax = cmp::min(sx2, (self.width - 1) as isize);
+    if sx1 == sx2 {
+      self.stats.culled += 1;
+      return;
+    }
+    self.stats.drawn += 1;
+
+    let xmin = cmp::max(sx1, self.columns.0 as isize);
+    let xmax = cmp::min(sx2, self.columns.1 as isize);
 
     // projection consts
     let a1 = self.p_z * (player.point.z - w.z);
     let a2 = self.p_z * (player.point.z - w.z - w.h);
-    let b = self.viewport.1 / 2.0;
+    let b = self.horizon(player);
     let c = (self.height as f32) / self.viewport.1;
 
     for x in xmin..xmax {
@@ -137,26 +461,36 @@ impl<'a> Renderer<'a> {
 
       let (sy1, sy2) = (cmp::min(sy1, sy2), cmp::max(sy1, sy2));
 
-      let tx = (tx * 32.0) as usize % 16;
+      let u = (tx + w.u_offset) / w.u_scale * TEX_REPEAT;
 
-      let ymin = cmp::max(sy1, 0);
-      let ymax = cmp::min(sy2, (self.height - 1) as isize);
+      let mut ymin = cmp::max(sy1, 0);
+      let mut ymax = cmp::min(sy2, self.height as isize);
+
+      // skip rows hidden by nearer walls
+      if let Some(ref mut coverage) = coverage {
+        if coverage.is_column_full(x as usize) {
+          continue;
+        }
+        let (top, bottom) = (coverage.top[x as usize], coverage.bottom[x as usize]);
+        coverage.cover(x as usize, ymin, ymax);
+        ymin = cmp::max(ymin, top);
+        ymax = cmp::min(ymax, bottom + 1);
+      }
 
       for y in ymin..ymax {
         let ky = (y - sy1) as f32 / (sy2 - sy1) as f32;
-        let ty = (ky * w.h * 32.0) as usize % 16;
 
-        if self.buffer.depth(x as usize, y as usize) > vz {
-          let (r, g, b);
-          {
-            let tex = self.textures[w.tex];
+        // distance from the pegged edge
+        let ty = match w.peg {
+          Peg::Top => ky * w.h,
+          Peg::Bottom => ky * w.h - w.h,
+        };
+        let v = (ty + w.v_offset) / w.v_scale * TEX_REPEAT;
 
-            r = tex[(ty * 16 + tx) * 3 + 0];
-            g = tex[(ty * 16 + tx) * 3 + 1];
-            b = tex[(ty * 16 + tx) * 3 + 2];
-          }
+        if self.buffer.depth(x as usize, y as usize) > vz {
+          let c = self.textures[w.tex].sample(u, v);
 
-          let tinted = color_mul(&[r, g, b], &w.color);
+          let tinted = color_mul(&[c[0], c[1], c[2]], &w.color);
           let color = color_mix(&tinted, &self.ambient, fade(vz));
 
           self.buffer.set_pixel3(x as usize, y as usize, vz, color);
@@ -175,6 +509,7 @@ impl<'a> Renderer<'a> {
 
     // clipped
     if vz <= self.p_z {
+      self.stats.culled += 1;
       return;
     }
 
@@ -183,7 +518,7 @@ impl<'a> Renderer<'a> {
     let cx = self.width as f32 / self.viewport.0;
 
     let ay = self.p_z;
-    let by = self.viewport.1 / 2.0;
+    let by = self.horizon(player);
     let cy = self.height as f32 / self.viewport.1;
 
     let sx = ((ax * vx / vz + bx) * cx) as isize;
@@ -196,31 +531,33 @@ impl<'a> Renderer<'a> {
     let sy1 = clamp(sy - sw / 2, 0, (self.height - 1) as isize);
     let sy2 = clamp(sy + sw / 2, 0, (self.height - 1) as isize);
 
+    // off screen
+    if sx1 >= sx2 || sy1 == sy2 {
+      self.stats.culled += 1;
+      return;
+    }
+    self.stats.drawn += 1;
+
+    let sx1 = cmp::max(sx1, self.columns.0 as isize);
+    let sx2 = cmp::min(sx2, self.columns.1 as isize);
+
     for x in sx1..sx2 {
       for y in sy1..sy2 {
         let kx = (x - sx + sw / 2) as f32 / sw as f32;
         let ky = (y - sy + sw / 2) as f32 / sw as f32;
 
-        let tx = (kx * 16.0) as usize % 16;
-        let ty = (ky * 16.0) as usize % 16;
-
         if self.buffer.depth(x as usize, y as usize) <= vz {
           continue;
         }
 
-        let (r, g, b);
-        {
-          let tex = self.textures[sprite.tex];
-          r = tex[(ty * 16 + tx) * 3 + 0];
-          g = tex[(ty * 16 + tx) * 3 + 1];
-          b = tex[(ty * 16 + tx) * 3 + 2];
+        let c = self.textures[sprite.tex].sample(kx, ky);
 
-          if (r == 0) && (g == 0) & (b == 0) {
-            continue;
-          }
+        // transparent or black color key
+        if c[3] == 0 || (c[0] == 0 && c[1] == 0 && c[2] == 0) {
+          continue;
         }
 
-        let tinted = &color_mul(&[r, g, b], &sprite.color);
+        let tinted = &color_mul(&[c[0], c[1], c[2]], &sprite.color);
         let color = color_mix(&tinted, &self.ambient, fade(f32::abs(vz)));
 
         self.buffer.set_pixel3(x as usize, y as usize, vz, color);
@@ -228,22 +565,46 @@ impl<'a> Renderer<'a> {
     }
   }
 
-  pub fn draw_background(&mut self, player: &Player) {
+  pub fn draw_background(&mut self, env: &Environment, player: &Player) {
+    if !self.spans {
+      self.draw_background_pixels(env, player);
+      return;
+    }
+
+    let ground = PlaneMapping::ground(env);
+    let (x1, x2) = (self.columns.0 as isize, self.columns.1 as isize);
+    let horizon = self.horizon(player) / self.viewport.1 * self.height as f32;
+
+    for y in 0..self.height {
+      // above the horizon or no ground at all
+      if (y as f32) < horizon || !env.ground {
+        for x in x1..x2 {
+          self.buffer.set_pixel(x as usize, y, self.ambient);
+        }
+        continue;
+      }
+
+      self.draw_plane_row(y as isize, x1, x2, &ground, false, player);
+    }
+  }
+
+  fn draw_background_pixels(&mut self, env: &Environment, player: &Player) {
     let cos = f32::cos(player.dir);
     let sin = f32::sin(player.dir);
 
-    for sx in 0..self.width {
+    for sx in self.columns.0..self.columns.1 {
       for sy in 0..self.height {
         let fsx = (sx as f32) / self.width as f32 * self.viewport.0 - self.viewport.0 / 2.0;
-        let fsy = (sy as f32) / self.height as f32 * self.viewport.1 - self.viewport.1 / 2.0;
+        let fsy = (sy as f32) / self.height as f32 * self.viewport.1 - self.horizon(player);
 
+        let above = fsy < 0.0;
         let fsy = f32::abs(fsy);
 
         let vz = self.p_z * player.point.z / fsy;
         let vx = fsx * vz / self.p_z;
 
-        // top half
-        if sy < self.height / 2 {
+        // above the horizon or no ground at all
+        if above || !env.ground {
           self.buffer.set_pixel(sx as usize, sy as usize, self.ambient);
 
           continue;
@@ -253,18 +614,9 @@ impl<'a> Renderer<'a> {
         let x = cos * vz - sin * -vx + player.point.x;
         let z = sin * vz + cos * -vx + player.point.y;
 
-        let tx = (x * 32.0) as usize % 16;
-        let ty = (z * 32.0) as usize % 16;
-
-        let (r, g, b);
-        {
-          let tex = self.textures[0];
-          r = tex[(ty * 16 + tx) * 3 + 0];
-          g = tex[(ty * 16 + tx) * 3 + 1];
-          b = tex[(ty * 16 + tx) * 3 + 2];
-        }
+        let c = self.textures[env.ground_tex].sample(x * TEX_REPEAT, z * TEX_REPEAT);
 
-        let tinted = color_mul(&[r, g, b], &[100, 255, 100]);
+        let tinted = color_mul(&[c[0], c[1], c[2]], &env.ground_tint);
         let color = color_mix(&tinted, &self.ambient, fade(f32::abs(vz)));
 
         self.buffer.set_pixel3(sx as usize, sy as usize, vz, color);
@@ -272,72 +624,179 @@ impl<'a> Renderer<'a> {
     }
   }
 
-  fn draw_polygon(&mut self,
-                  points: &Vec<(isize, isize)>,
-                  height: f32,
-                  player: &Player,
-                  tex: usize,
-                  tint: &[u8; 3]) {
-    // index of leftmost point
-    let min_index = points.iter()
-      .enumerate()
-      .min_by_key(|&(_, x)| x.0)
-      .unwrap()
-      .0;
+  pub fn draw_sky(&mut self, sky: &Sky, player: &Player) {
+    let pi = std::f32::consts::PI;
+    let half = self.height as f32 / 2.0;
+    let horizon = self.horizon(player) / self.viewport.1 * self.height as f32;
 
-    // clock- and counterclock- wise iterators
-    let mut top = points.iter().cycle().skip(min_index);
-    let mut bottom = points.iter()
-      .rev()
-      .cycle()
-      .skip(points.len() - min_index - 1);
+    for sx in self.columns.0..self.columns.1 {
+      let fsx = (sx as f32) / self.width as f32 * self.viewport.0 - self.viewport.0 / 2.0;
 
-    // prev points in top & bottom chain
-    let mut ppt = top.next().unwrap();
-    let mut ppb = bottom.next().unwrap();
+      // panorama wraps once around the player
+      let angle = player.dir - f32::atan2(fsx, self.p_z);
+      let u = -angle / (2.0 * pi);
 
-    // current points in top & bottom chain
-    let mut pt = top.next().unwrap();
-    let mut pb = bottom.next().unwrap();
+      for sy in 0..self.height {
+        // something is already drawn here
+        if self.buffer.depth(sx, sy) < f32::MAX {
+          continue;
+        }
 
-    let px0 = ppt;
-    let x0 = px0.0;
+        let v = clamp_f((sy as f32 - horizon + half) / half, 0.0, 0.999);
 
-    let px1 = points.iter().max_by_key(|x| x.0).unwrap();
-    let x1 = px1.0;
+        let gradient = sky.gradient.map(|g| color_mix(&g[1], &g[0], v));
 
-    let mut xt = x0;
-    let mut xb = x0;
+        let mut color = match (sky.tex, gradient) {
+          (Some(tex), Some(g)) => {
+            let c = self.textures[tex].sample(u, v);
+            color_mul(&[c[0], c[1], c[2]], &g)
+          }
+          (Some(tex), None) => {
+            let c = self.textures[tex].sample(u, v);
+            [c[0], c[1], c[2]]
+          }
+          (None, Some(g)) => g,
+          (None, None) => self.ambient,
+        };
 
-    let xmin = cmp::max(x0, 0);
-    let xmax = cmp::min(x1 + 1, self.width as isize);
+        if let Some(ref clouds) = sky.clouds {
+          // repeated twice around the panorama
+          let c = self.textures[clouds.tex].sample((u + self.t * clouds.speed) * 2.0, v);
 
+          // black is clear sky
+          if c[0] != 0 || c[1] != 0 || c[2] != 0 {
+            let k = c[3] as f32 / 255.0 * clouds.opacity;
+            color = color_mix(&[c[0], c[1], c[2]], &color, clamp_f(k, 0.0, 1.0));
+          }
+        }
+
+        self.buffer.set_pixel(sx, sy, color);
+      }
+    }
+  }
+
+  fn draw_polygon(&mut self, points: &[(isize, isize)], floor: &Floor, player: &Player) {
+    if !self.spans {
+      self.draw_polygon_pixels(points, floor, player);
+      return;
+    }
+
+    let y0 = points.iter().map(|p| p.1).min().unwrap();
+    let y1 = points.iter().map(|p| p.1).max().unwrap();
+
+    let ymin = cmp::max(y0, 0);
+    let ymax = cmp::min(y1 + 1, self.height as isize);
+
+    let mapping = PlaneMapping::floor(floor);
+
+    for y in ymin..ymax {
+      // row span between the edges crossing it
+      let mut span: Option<(isize, isize)> = None;
+
+      let seq = points.iter().zip(points.iter().cycle().skip(1));
+      for (p1, p2) in seq {
+        if p1.1 == p2.1 || y < cmp::min(p1.1, p2.1) || y > cmp::max(p1.1, p2.1) {
+          continue;
+        }
+
+        let x = p1.0 + (y - p1.1) * (p2.0 - p1.0) / (p2.1 - p1.1);
+        span = Some(span.map_or((x, x), |(x1, x2)| (cmp::min(x1, x), cmp::max(x2, x))));
+      }
+
+      let (xmin, xmax) = match span {
+        Some(span) => span,
+        None => continue,
+      };
+
+      let xmin = cmp::max(xmin, self.columns.0 as isize);
+      let xmax = cmp::min(xmax + 1, self.columns.1 as isize);
+
+      self.draw_plane_row(y, xmin, xmax, &mapping, true, player);
+    }
+  }
+
+  // draws columns x1..x2 of a plane row, depth is constant along the
+  // row so texture coords only need a step per column
+  fn draw_plane_row(&mut self,
+                    y: isize,
+                    x1: isize,
+                    x2: isize,
+                    plane: &PlaneMapping,
+                    depth_test: bool,
+                    player: &Player) {
     let cos = f32::cos(player.dir);
     let sin = f32::sin(player.dir);
+    let (rcos, rsin) = (f32::cos(plane.rotation), f32::sin(plane.rotation));
 
-    for x in xmin..xmax {
-      // next point in top chain
-      if pt.0 < x {
-        ppt = pt;
-        pt = top.next().unwrap();
-        xt = x;
-      }
-      // next point in bottom chain
-      if pb.0 < x {
-        ppb = pb;
-        pb = bottom.next().unwrap();
-        xb = x;
+    let fsy = (y as f32) / self.height as f32 * self.viewport.1 - self.horizon(player);
+    let fsy = fsy.abs();
+
+    // to view space, vx at column 0 and its step
+    let vz = self.p_z * (player.point.z - plane.height).abs() / fsy;
+    let vx = -self.viewport.0 / 2.0 * vz / self.p_z;
+    let dvx = self.viewport.0 / self.width as f32 * vz / self.p_z;
+
+    // to world space
+    let wx = cos * vz - sin * -vx + player.point.x;
+    let wz = sin * vz + cos * -vx + player.point.y;
+    let (dwx, dwz) = (sin * dvx, -cos * dvx);
+
+    // to texture space
+    let k = (TEX_REPEAT / plane.scale.0, TEX_REPEAT / plane.scale.1);
+    let u = (rcos * wx - rsin * wz + plane.offset.0) * k.0;
+    let v = (rsin * wx + rcos * wz + plane.offset.1) * k.1;
+    let du = (rcos * dwx - rsin * dwz) * k.0;
+    let dv = (rsin * dwx + rcos * dwz) * k.1;
+
+    let fade = fade(vz);
+
+    for x in x1..x2 {
+      if depth_test && self.buffer.depth(x as usize, y as usize) <= vz {
+        continue;
       }
 
-      if pt.0 - ppt.0 == 0 { continue; }
-      if pb.0 - ppb.0 == 0 { continue; }
-        
-      // interpolate y for segments
-      let yt = ppt.1 + (x - xt) * (pt.1 - ppt.1) / (pt.0 - ppt.0);
-      let yb = ppb.1 + (x - xb) * (pb.1 - ppb.1) / (pb.0 - ppb.0);
+      // stepped from column 0, strips get the same coords
+      let c = self.textures[plane.tex].sample(u + du * x as f32, v + dv * x as f32);
+
+      let tinted = color_mul(&[c[0], c[1], c[2]], &plane.tint);
+      let color = color_mix(&tinted, &self.ambient, fade);
+
+      self.buffer.set_pixel3(x as usize, y as usize, vz, color);
+    }
+  }
+
+  fn draw_polygon_pixels(&mut self, points: &[(isize, isize)], floor: &Floor, player: &Player) {
+    let x0 = points.iter().map(|p| p.0).min().unwrap();
+    let x1 = points.iter().map(|p| p.0).max().unwrap();
 
-      let ymin = cmp::min(yt, yb);
-      let ymax = cmp::max(yt, yb);
+    let xmin = cmp::max(x0, self.columns.0 as isize);
+    let xmax = cmp::min(x1 + 1, self.columns.1 as isize);
+
+    let cos = f32::cos(player.dir);
+    let sin = f32::sin(player.dir);
+
+    // texture rotation
+    let rot = floor.rotation.to_radians();
+    let (rcos, rsin) = (f32::cos(rot), f32::sin(rot));
+
+    for x in xmin..xmax {
+      // column span between the edges crossing it
+      let mut span: Option<(isize, isize)> = None;
+
+      let seq = points.iter().zip(points.iter().cycle().skip(1));
+      for (p1, p2) in seq {
+        if p1.0 == p2.0 || x < cmp::min(p1.0, p2.0) || x > cmp::max(p1.0, p2.0) {
+          continue;
+        }
+
+        let y = p1.1 + (x - p1.0) * (p2.1 - p1.1) / (p2.0 - p1.0);
+        span = Some(span.map_or((y, y), |(y1, y2)| (cmp::min(y1, y), cmp::max(y2, y))));
+      }
+
+      let (ymin, ymax) = match span {
+        Some(span) => span,
+        None => continue,
+      };
 
       let ymin = cmp::max(ymin, 0);
       let ymax = cmp::min(ymax + 1, self.height as isize);
@@ -345,11 +804,11 @@ impl<'a> Renderer<'a> {
       let fsx = (x as f32) / self.width as f32 * self.viewport.0 - self.viewport.0 / 2.0;
 
       for y in ymin..ymax {
-        let fsy = (y as f32) / self.height as f32 * self.viewport.1 - self.viewport.1 / 2.0;
+        let fsy = (y as f32) / self.height as f32 * self.viewport.1 - self.horizon(player);
         let fsy = fsy.abs();
 
         // to view space
-        let vz = self.p_z * (player.point.z - height) / fsy;
+        let vz = self.p_z * (player.point.z - floor.height).abs() / fsy;
         let vx = fsx * vz / self.p_z;
 
         if self.buffer.depth(x as usize, y as usize) <= vz {
@@ -360,20 +819,13 @@ impl<'a> Renderer<'a> {
         let wx = cos * vz - sin * -vx + player.point.x;
         let wz = sin * vz + cos * -vx + player.point.y;
 
-        let xt = (wx * 32.0).abs() as usize % 16;
-        let yt = (wz * 32.0).abs() as usize % 16;
+        // to texture space
+        let tu = (rcos * wx - rsin * wz + floor.u_offset) / floor.u_scale;
+        let tv = (rsin * wx + rcos * wz + floor.v_offset) / floor.v_scale;
 
-        let (r, g, b);
-        {
-          let tex = &self.textures[tex];
-          let base = yt * 16 + xt;
+        let c = self.textures[floor.tex].sample(tu * TEX_REPEAT, tv * TEX_REPEAT);
 
-          r = tex[base * 3 + 0];
-          g = tex[base * 3 + 1];
-          b = tex[base * 3 + 2];
-        };
-
-        let tinted = color_mul(&[r, g, b], tint);
+        let tinted = color_mul(&[c[0], c[1], c[2]], &floor.color);
         let color = color_mix(&tinted, &self.ambient, fade(vz));
 
         self.buffer.set_pixel3(x as usize, y as usize, vz, color);
@@ -381,57 +833,284 @@ impl<'a> Renderer<'a> {
     }
   }
 
-  pub fn draw_floor(&mut self, floor: &Floor, player: &Player) {
-    if floor.height >= player.point.z {
+  pub fn draw_sectors(&mut self, sectors: &[Sector], start: usize, player: &Player) {
+    // open rows per column, inclusive
+    let mut ytop = vec![0; self.width];
+    let mut ybottom = vec![self.height as isize - 1; self.width];
+
+    let mut window = Window {
+      columns: (self.columns.0 as isize, self.columns.1 as isize),
+      ytop: &mut ytop,
+      ybottom: &mut ybottom,
+    };
+    self.draw_sector(sectors, start, &mut window, 0, player);
+  }
+
+  // draws columns of the sector within the window, recurses into
+  // portals narrowing the window
+  fn draw_sector(&mut self, sectors: &[Sector], index: usize, window: &mut Window, depth: usize, player: &Player) {
+    if depth > MAX_PORTAL_DEPTH {
       return;
     }
-    
-    // zip points to segments
-    let seq = floor.points.iter().zip(floor.points
-                                        .iter()
-                                        .cycle()
-                                        .skip(1));
-
-    // project points x coords to screen
-    let points: Vec<_> = {
-      let projected = seq.map(|(p1, p2)| {
-        self.project_segment(&Point3 {
-                                x: p1.x,
-                                y: p1.y,
-                                z: floor.height,
-                              },
-                             &Point3 {
-                                x: p2.x,
-                                y: p2.y,
-                                z: floor.height,
-                              },
-                             player)
-      });
-
-      // projection consts
-      let a = self.p_z * (player.point.z - floor.height);
-      let b = self.viewport.1 / 2.0;
-      let c = self.height as f32 / self.viewport.1;
-
-      // filter off screen segments and calc ys
-      let filter = |seg| if let Some((p0, _)) = seg {
-        let (sx0, _, z0, _) = p0;
-
-        let sy0 = ((a / z0 + b) * c) as isize;
-
-        Some((sx0 as isize, sy0))
-      } else {
-        //FIXME: fix next segment coords if prev. one was thrown out
-        None
+
+    let s = &sectors[index];
+    let ceiling = PlaneMapping::plain(s.ceiling, s.ceiling_tex, s.color);
+    let floor = PlaneMapping::plain(s.floor, s.floor_tex, s.color);
+
+    // projection consts
+    let a = self.p_z;
+    let b = self.horizon(player);
+    let c = (self.height as f32) / self.viewport.1;
+    let eye = player.point.z;
+    let screen_y = |h: f32, vz: f32| (a * (eye - h) / vz + b) * c;
+
+    for i in 0..s.edges.len() {
+      let e = &s.edges[i];
+      let (p1, p2) = s.edge(i);
+
+      let p1 = Point3 {
+        x: p1[0],
+        y: p1[1],
+        z: 0.0,
+      };
+      let p2 = Point3 {
+        x: p2[0],
+        y: p2[1],
+        z: 0.0,
       };
 
-      projected.filter_map(filter).collect()
-    };
+      let (start, end) = match self.project_segment(&p1, &p2, player, SECTOR_NEAR) {
+        Some(p) => p,
+        None => continue,
+      };
+
+      // edges facing away go left to right
+      if start.0 <= end.0 {
+        continue;
+      }
+
+      let (sx1, _, vz1, tx1) = end;
+      let (sx2, _, vz2, tx2) = start;
+
+      let xmin = cmp::max(sx1, window.columns.0);
+      let xmax = cmp::min(sx2, window.columns.1);
+
+      let neighbour = e.portal.map(|p| &sectors[p]);
+      let tex = e.tex.unwrap_or(s.wall_tex);
+
+      for x in xmin..xmax {
+        let col = x as usize;
+        let (top, bottom) = (window.ytop[col], window.ybottom[col]);
+        if top > bottom {
+          continue;
+        }
+
+        // interpolate 1/z and tx/z
+        let kx = ((x - sx1) as f32) / ((sx2 - sx1) as f32);
+        let vz = (vz1 * vz2) / ((1.0 - kx) * vz2 + kx * vz1);
+        let tx = (tx1 / vz1 + (tx2 / vz2 - tx1 / vz1) * kx) * vz;
+
+        let yc = screen_y(s.ceiling, vz);
+        let yf = screen_y(s.floor, vz);
+        let (syc, syf) = (yc as isize, yf as isize);
+
+        // ceiling above and floor below the wall
+        let ceiling_end = cmp::min(syc, bottom + 1);
+        self.draw_plane_span(x, (top, ceiling_end), &ceiling, player);
+
+        let floor_start = cmp::max(syf, top);
+        self.draw_plane_span(x, (floor_start, bottom + 1), &floor, player);
+
+        let wall = WallColumn {
+          x: x,
+          vz: vz,
+          tx: tx,
+          yc: yc,
+          yf: yf,
+          ceiling: s.ceiling,
+          floor: s.floor,
+          tex: tex,
+          tint: s.color,
+        };
+
+        match neighbour {
+          None => {
+            self.draw_wall_span(&wall, cmp::max(syc, top), cmp::min(syf, bottom + 1));
+
+            // column is closed
+            window.ytop[col] = bottom + 1;
+          }
+          Some(n) => {
+            let snc = screen_y(n.ceiling, vz) as isize;
+            let snf = screen_y(n.floor, vz) as isize;
+
+            // upper and lower walls around the portal
+            if snc > syc {
+              self.draw_wall_span(&wall, cmp::max(syc, top), cmp::min(snc, bottom + 1));
+            }
+            if snf < syf {
+              self.draw_wall_span(&wall, cmp::max(snf, top), cmp::min(syf, bottom + 1));
+            }
+
+            window.ytop[col] = cmp::max(top, cmp::max(syc, snc));
+            window.ybottom[col] = cmp::min(bottom, cmp::min(syf, snf) - 1);
+          }
+        }
+      }
+
+      if let Some(p) = e.portal {
+        if xmin < xmax {
+          let mut inner = Window {
+            columns: (xmin, xmax),
+            ytop: &mut *window.ytop,
+            ybottom: &mut *window.ybottom,
+          };
+          self.draw_sector(sectors, p, &mut inner, depth + 1, player);
+        }
+      }
+    }
+  }
+
+  // rows y1..y2 of a sector wall column
+  fn draw_wall_span(&mut self, wall: &WallColumn, y1: isize, y2: isize) {
+    let u = wall.tx * TEX_REPEAT;
+
+    for y in y1..y2 {
+      // world height, texture is pegged to the ceiling
+      let z = wall.ceiling - (y as f32 - wall.yc) / (wall.yf - wall.yc) * (wall.ceiling - wall.floor);
+      let v = (wall.ceiling - z) * TEX_REPEAT;
+
+      let c = self.textures[wall.tex].sample(u, v);
+
+      let tinted = color_mul(&[c[0], c[1], c[2]], &wall.tint);
+      let color = color_mix(&tinted, &self.ambient, fade(wall.vz));
+
+      self.buffer.set_pixel3(wall.x as usize, y as usize, wall.vz, color);
+    }
+  }
+
+  // rows of a column showing a horizontal plane
+  fn draw_plane_span(&mut self, x: isize, rows: (isize, isize), plane: &PlaneMapping, player: &Player) {
+    let cos = f32::cos(player.dir);
+    let sin = f32::sin(player.dir);
+    let (rcos, rsin) = (f32::cos(plane.rotation), f32::sin(plane.rotation));
+    let k = (TEX_REPEAT / plane.scale.0, TEX_REPEAT / plane.scale.1);
+
+    let fsx = (x as f32) / self.width as f32 * self.viewport.0 - self.viewport.0 / 2.0;
+
+    for y in rows.0..rows.1 {
+      let fsy = (y as f32) / self.height as f32 * self.viewport.1 - self.horizon(player);
+      let fsy = fsy.abs();
+
+      // to view space
+      let vz = self.p_z * (player.point.z - plane.height).abs() / fsy;
+      let vx = fsx * vz / self.p_z;
+
+      // to world space
+      let wx = cos * vz - sin * -vx + player.point.x;
+      let wz = sin * vz + cos * -vx + player.point.y;
+
+      // to texture space
+      let u = (rcos * wx - rsin * wz + plane.offset.0) * k.0;
+      let v = (rsin * wx + rcos * wz + plane.offset.1) * k.1;
+
+      let c = self.textures[plane.tex].sample(u, v);
+
+      let tinted = color_mul(&[c[0], c[1], c[2]], &plane.tint);
+      let color = color_mix(&tinted, &self.ambient, fade(vz));
+
+      self.buffer.set_pixel3(x as usize, y as usize, vz, color);
+    }
+  }
+
+  // clips a view space polygon to the part where f(vx, vz) >= 0
+  fn clip_polygon<F>(points: &[(f32, f32)], f: F) -> Vec<(f32, f32)>
+    where F: Fn(f32, f32) -> f32
+  {
+    let mut clipped = vec![];
+
+    let seq = points.iter().zip(points.iter().cycle().skip(1));
+    for (&(x1, z1), &(x2, z2)) in seq {
+      let d1 = f(x1, z1);
+      let d2 = f(x2, z2);
+
+      if d1 >= 0.0 {
+        clipped.push((x1, z1));
+      }
+
+      // edge crosses the plane
+      if (d1 >= 0.0) != (d2 >= 0.0) {
+        let k = d1 / (d1 - d2);
+        clipped.push((x1 + (x2 - x1) * k, z1 + (z2 - z1) * k));
+      }
+    }
+
+    clipped
+  }
+
+  pub fn draw_floor(&mut self, floor: &Floor, player: &Player) {
+    // floors are visible from above, ceilings from below
+    if floor.ceiling != (floor.height > player.point.z) || floor.height == player.point.z {
+      self.stats.culled += cmp::max(floor.triangles.len(), 1);
+      return;
+    }
+
+    // not triangulated, draw as is
+    if floor.triangles.is_empty() {
+      let points: Vec<_> = floor.points.iter().map(|p| [p.x, p.y]).collect();
+      self.draw_floor_part(&points, floor, player);
+    }
+
+    for t in &floor.triangles {
+      self.draw_floor_part(t, floor, player);
+    }
+  }
+
+  fn draw_floor_part(&mut self, points: &[[f32; 2]], floor: &Floor, player: &Player) {
+    let cos = f32::cos(-player.dir);
+    let sin = f32::sin(-player.dir);
+
+    // view space coords
+    let points: Vec<_> = points.iter()
+      .map(|p| {
+        let vx = cos * -(p[1] - player.point.y) - sin * (p[0] - player.point.x);
+        let vz = sin * -(p[1] - player.point.y) + cos * (p[0] - player.point.x);
+        (vx, vz)
+      })
+      .collect();
+
+    // clip against near, left and right planes
+    let k = self.viewport.0 / 2.0 / self.p_z;
+    let p_z = self.p_z;
+
+    let points = Renderer::clip_polygon(&points, |_, vz| vz - p_z);
+    let points = Renderer::clip_polygon(&points, |vx, vz| vx + k * vz);
+    let points = Renderer::clip_polygon(&points, |vx, vz| k * vz - vx);
 
     if points.len() < 3 {
+      self.stats.culled += 1;
       return;
     }
+    self.stats.drawn += 1;
+
+    // projection consts
+    let ax = self.p_z;
+    let bx = self.viewport.0 / 2.0;
+    let cx = self.width as f32 / self.viewport.0;
+
+    let ay = self.p_z * (player.point.z - floor.height);
+    let by = self.horizon(player);
+    let cy = self.height as f32 / self.viewport.1;
+
+    // project to the screen space
+    let points: Vec<_> = points.iter()
+      .map(|&(vx, vz)| {
+        let sx = ((ax * vx / vz + bx) * cx) as isize;
+        let sy = ((ay / vz + by) * cy) as isize;
+        (sx, sy)
+      })
+      .collect();
 
-    self.draw_polygon(&points, floor.height, &player, floor.tex, &floor.color);
+    self.draw_polygon(&points, floor, player);
   }
 }
@@ -0,0 +1,56 @@
+use game::*;
+use input::*;
+
+use serde_json;
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+
+// input of every update since the level was loaded
+#[derive(Serialize, Deserialize)]
+pub struct Recording {
+  pub level: String,
+  // seconds per update
+  pub tick: f64,
+  pub inputs: Vec<Input>,
+}
+
+impl Recording {
+  pub fn new(level: &str, tick: f64) -> Recording {
+    Recording {
+      level: level.to_owned(),
+      tick: tick,
+      inputs: vec![],
+    }
+  }
+
+  // call before every update
+  pub fn record(&mut self, game: &Game) {
+    self.inputs.push(game.input());
+  }
+
+  // feeds the inputs to a freshly loaded game, f is called after
+  // every update with its number
+  pub fn replay<F>(&self, game: &mut Game, mut f: F)
+    where F: FnMut(usize, &mut Game)
+  {
+    for (i, input) in self.inputs.iter().enumerate() {
+      game.set_input(*input);
+      game.update(self.tick);
+      f(i, game);
+    }
+  }
+
+  pub fn save(&self, path: &str) -> io::Result<()> {
+    let data = serde_json::to_string(self)
+      .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    File::create(path)?.write_all(data.as_bytes())
+  }
+
+  pub fn load(path: &str) -> io::Result<Recording> {
+    let mut data = String::new();
+    File::open(path)?.read_to_string(&mut data)?;
+    serde_json::from_str(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+  }
+}
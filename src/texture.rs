@@ -0,0 +1,87 @@
+#[derive(Clone)]
+pub enum Format {
+  Rgb24,
+  Rgba32,
+  // one byte per pixel indexing into the palette
+  Indexed(Vec<[u8; 3]>),
+}
+
+impl Format {
+  pub fn bytes_per_pixel(&self) -> usize {
+    match *self {
+      Format::Rgb24 => 3,
+      Format::Rgba32 => 4,
+      Format::Indexed(_) => 1,
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct Texture<'a> {
+  pub pixels: &'a [u8],
+  pub width: usize,
+  pub height: usize,
+  // bytes per row
+  pub pitch: usize,
+  pub format: Format,
+}
+
+impl<'a> Texture<'a> {
+  pub fn new(pixels: &'a [u8],
+             width: usize,
+             height: usize,
+             pitch: usize,
+             format: Format)
+             -> Result<Texture<'a>, String> {
+    if width == 0 || height == 0 {
+      return Err(format!("empty texture {}x{}", width, height));
+    }
+
+    let row = width * format.bytes_per_pixel();
+    if pitch < row {
+      return Err(format!("pitch {} is less than row size {}", pitch, row));
+    }
+
+    if pixels.len() < pitch * (height - 1) + row {
+      return Err(format!("{} bytes is not enough for {}x{} texture with pitch {}",
+                         pixels.len(),
+                         width,
+                         height,
+                         pitch));
+    }
+
+    Ok(Texture {
+      pixels: pixels,
+      width: width,
+      height: height,
+      pitch: pitch,
+      format: format,
+    })
+  }
+
+  // RGBA texel, coords wrap around
+  pub fn texel(&self, tx: isize, ty: isize) -> [u8; 4] {
+    let tx = tx.rem_euclid(self.width as isize) as usize;
+    let ty = ty.rem_euclid(self.height as isize) as usize;
+
+    let base = ty * self.pitch + tx * self.format.bytes_per_pixel();
+    let p = &self.pixels[base..];
+
+    match self.format {
+      Format::Rgb24 => [p[0], p[1], p[2], 255],
+      Format::Rgba32 => [p[0], p[1], p[2], p[3]],
+      Format::Indexed(ref palette) => {
+        let c = palette.get(p[0] as usize).unwrap_or(&[0, 0, 0]);
+        [c[0], c[1], c[2], 255]
+      }
+    }
+  }
+
+  // samples texture at (u, v) measured in texture sizes
+  pub fn sample(&self, u: f32, v: f32) -> [u8; 4] {
+    let tx = (u * self.width as f32).floor() as isize;
+    let ty = (v * self.height as f32).floor() as isize;
+
+    self.texel(tx, ty)
+  }
+}
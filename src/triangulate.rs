@@ -0,0 +1,171 @@
+use util::*;
+
+use std;
+
+// twice the signed area, positive for counterclockwise polygons
+fn area(points: &[[f32; 2]]) -> f32 {
+  let seq = points.iter().zip(points.iter().cycle().skip(1));
+  seq.fold(0.0, |a, (p1, p2)| a + vec_cross(p1, p2))
+}
+
+fn is_convex(a: &[f32; 2], b: &[f32; 2], c: &[f32; 2]) -> bool {
+  vec_cross(&vec_sub(b, a), &vec_sub(c, b)) > 0.0
+}
+
+// inside or on the border, any orientation
+fn in_triangle(p: &[f32; 2], a: &[f32; 2], b: &[f32; 2], c: &[f32; 2]) -> bool {
+  let d1 = vec_cross(&vec_sub(b, a), &vec_sub(p, a));
+  let d2 = vec_cross(&vec_sub(c, b), &vec_sub(p, b));
+  let d3 = vec_cross(&vec_sub(a, c), &vec_sub(p, c));
+
+  (d1 >= 0.0 && d2 >= 0.0 && d3 >= 0.0) || (d1 <= 0.0 && d2 <= 0.0 && d3 <= 0.0)
+}
+
+// connects a clockwise hole to the counterclockwise outline with a
+// bridge from the rightmost hole vertex to a vertex visible from it
+fn merge_hole(outline: &mut Vec<[f32; 2]>, hole: &[[f32; 2]]) -> bool {
+  let (m_index, m) = hole.iter()
+    .cloned()
+    .enumerate()
+    .fold((0, hole[0]), |best, (i, p)| if p[0] > best.1[0] { (i, p) } else { best });
+
+  // closest outline edge hit by a ray going right from m
+  let n = outline.len();
+  let mut hit: Option<(f32, usize)> = None;
+
+  for i in 0..n {
+    let a = outline[i];
+    let b = outline[(i + 1) % n];
+
+    if a[1] == b[1] || m[1] < f32::min(a[1], b[1]) || m[1] > f32::max(a[1], b[1]) {
+      continue;
+    }
+
+    let x = a[0] + (m[1] - a[1]) * (b[0] - a[0]) / (b[1] - a[1]);
+    if x >= m[0] && hit.is_none_or(|(hx, _)| x < hx) {
+      hit = Some((x, i));
+    }
+  }
+
+  let (hx, edge) = match hit {
+    Some(hit) => hit,
+    None => return false,
+  };
+
+  // edge end point furthest right
+  let i = [edge, (edge + 1) % n];
+  let mut p_index = if outline[i[0]][0] > outline[i[1]][0] { i[0] } else { i[1] };
+  let p = outline[p_index];
+
+  // reflex vertices inside the (m, hit point, p) triangle may block the
+  // view, take the one with the smallest angle to the ray instead
+  let hit_point = [hx, m[1]];
+
+  let mut best = -1.0;
+  for j in 0..n {
+    let prev = outline[(j + n - 1) % n];
+    let v = outline[j];
+    let next = outline[(j + 1) % n];
+
+    if j == p_index || is_convex(&prev, &v, &next) || !in_triangle(&v, &m, &hit_point, &p) {
+      continue;
+    }
+
+    let d = vec_sub(&v, &m);
+    let cos = d[0] / vec_len(&v, &m);
+    if cos > best {
+      best = cos;
+      p_index = j;
+    }
+  }
+
+  // outline up to p, the hole starting and ending at m, back to p
+  let mut merged = Vec::with_capacity(n + hole.len() + 2);
+  merged.extend_from_slice(&outline[..p_index + 1]);
+  for k in 0..hole.len() + 1 {
+    merged.push(hole[(m_index + k) % hole.len()]);
+  }
+  merged.extend_from_slice(&outline[p_index..]);
+
+  *outline = merged;
+  true
+}
+
+// ear clipping of a simple polygon with holes
+pub fn triangulate(points: &[[f32; 2]], holes: &[Vec<[f32; 2]>]) -> Option<Vec<[[f32; 2]; 3]>> {
+  let mut outline = points.to_vec();
+  if area(&outline) < 0.0 {
+    outline.reverse();
+  }
+
+  // rightmost holes go first so that bridges do not cross
+  let mut holes: Vec<Vec<[f32; 2]>> = holes.iter()
+    .map(|h| {
+      let mut h = h.clone();
+      if area(&h) > 0.0 {
+        h.reverse();
+      }
+      h
+    })
+    .collect();
+  holes.sort_by(|a, b| {
+    let ax = a.iter().fold(f32::MIN, |x, p| f32::max(x, p[0]));
+    let bx = b.iter().fold(f32::MIN, |x, p| f32::max(x, p[0]));
+    bx.partial_cmp(&ax).unwrap()
+  });
+
+  for h in &holes {
+    if h.len() < 3 || !merge_hole(&mut outline, h) {
+      return None;
+    }
+  }
+
+  let mut triangles = vec![];
+  let mut v = outline;
+
+  while v.len() > 3 {
+    let n = v.len();
+
+    let ear = (0..n).find(|&i| {
+      let a = v[(i + n - 1) % n];
+      let b = v[i];
+      let c = v[(i + 1) % n];
+
+      if !is_convex(&a, &b, &c) {
+        return false;
+      }
+
+      // no other vertex inside, bridge duplicates do not count
+      !v.iter().any(|p| *p != a && *p != b && *p != c && in_triangle(p, &a, &b, &c))
+    });
+
+    // degenerate leftovers, drop collinear points
+    let i = match ear {
+      Some(i) => i,
+      None => {
+        match (0..n).find(|&i| vec_cross(&vec_sub(&v[i], &v[(i + n - 1) % n]),
+                                         &vec_sub(&v[(i + 1) % n], &v[i])) == 0.0) {
+          Some(i) => {
+            v.remove(i);
+            continue;
+          }
+          None => return None,
+        }
+      }
+    };
+
+    triangles.push([v[(i + n - 1) % n], v[i], v[(i + 1) % n]]);
+    v.remove(i);
+  }
+
+  if v.len() == 3 && is_convex(&v[0], &v[1], &v[2]) {
+    triangles.push([v[0], v[1], v[2]]);
+  }
+
+  // nothing left after dropping collinear points
+  if triangles.is_empty() {
+    return None;
+  }
+
+  Some(triangles)
+}
@@ -1,5 +1,7 @@
 use std::cmp;
 
+use data::Point2;
+
 pub fn color_mul(c1: &[u8; 3], c2: &[u8; 3]) -> [u8; 3] {
   [(((c1[0] as usize) << 8) * ((c2[0] as usize) << 8) >> 24) as u8,
    (((c1[1] as usize) << 8) * ((c2[1] as usize) << 8) >> 24) as u8,
@@ -16,10 +18,77 @@ pub fn vec_len(v1: &[f32; 2], v2: &[f32; 2]) -> f32 {
   f32::sqrt((v2[0] - v1[0]) * (v2[0] - v1[0]) + (v2[1] - v1[1]) * (v2[1] - v1[1]))
 }
 
+pub fn vec_sub(v1: &[f32; 2], v2: &[f32; 2]) -> [f32; 2] {
+  [v1[0] - v2[0], v1[1] - v2[1]]
+}
+
+pub fn vec_dot(v1: &[f32; 2], v2: &[f32; 2]) -> f32 {
+  v1[0] * v2[0] + v1[1] * v2[1]
+}
+
+pub fn vec_cross(v1: &[f32; 2], v2: &[f32; 2]) -> f32 {
+  v1[0] * v2[1] - v1[1] * v2[0]
+}
+
+// segments (a, b) and (c, d) share at least one point
+pub fn segments_intersect(a: &[f32; 2], b: &[f32; 2], c: &[f32; 2], d: &[f32; 2]) -> bool {
+  let d1 = vec_cross(&vec_sub(b, a), &vec_sub(c, a));
+  let d2 = vec_cross(&vec_sub(b, a), &vec_sub(d, a));
+  let d3 = vec_cross(&vec_sub(d, c), &vec_sub(a, c));
+  let d4 = vec_cross(&vec_sub(d, c), &vec_sub(b, c));
+
+  if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) &&
+     ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0)) {
+    return true;
+  }
+
+  // collinear cases
+  let on_segment = |p: &[f32; 2], q: &[f32; 2], r: &[f32; 2]| {
+    f32::min(p[0], q[0]) <= r[0] && r[0] <= f32::max(p[0], q[0]) &&
+    f32::min(p[1], q[1]) <= r[1] && r[1] <= f32::max(p[1], q[1])
+  };
+
+  (d1 == 0.0 && on_segment(a, b, c)) || (d2 == 0.0 && on_segment(a, b, d)) ||
+  (d3 == 0.0 && on_segment(c, d, a)) || (d4 == 0.0 && on_segment(c, d, b))
+}
+
+// even-odd rule
+pub fn point_in_polygon(p: &[f32; 2], points: &[Point2]) -> bool {
+  let mut inside = false;
+
+  let seq = points.iter().zip(points.iter().cycle().skip(1));
+  for (p1, p2) in seq {
+    if (p1.y > p[1]) != (p2.y > p[1]) {
+      let x = p1.x + (p[1] - p1.y) * (p2.x - p1.x) / (p2.y - p1.y);
+      if p[0] < x {
+        inside = !inside;
+      }
+    }
+  }
+
+  inside
+}
+
 pub fn clamp<T>(x: T, mn: T, mx: T) -> T where T: cmp::Ord {
   cmp::min(cmp::max(x, mn), mx)
 }
 
+pub fn clamp_f(x: f32, mn: f32, mx: f32) -> f32 {
+  f32::min(f32::max(x, mn), mx)
+}
+
 pub fn fade(z: f32) -> f32 {
   f32::min(f32::max(0.0, 1.0 / (f32::powf(z * 0.2, 1.2) + 1.0)), 1.0)
 }
+
+// largest rect with the aspect of src centered in dst as (x, y, w, h),
+// integer scaling falls back to fractional when dst is smaller than src
+pub fn fit_rect(src: (usize, usize), dst: (usize, usize), integer: bool) -> (usize, usize, usize, usize) {
+  let scale = f32::min(dst.0 as f32 / src.0 as f32, dst.1 as f32 / src.1 as f32);
+  let scale = if integer && scale >= 1.0 { scale.floor() } else { scale };
+
+  let w = cmp::min((src.0 as f32 * scale) as usize, dst.0);
+  let h = cmp::min((src.1 as f32 * scale) as usize, dst.1);
+
+  ((dst.0 - w) / 2, (dst.1 - h) / 2, w, h)
+}
@@ -0,0 +1,130 @@
+extern crate raster;
+extern crate serde_json;
+
+mod common;
+
+use raster::bsp::*;
+use raster::data::*;
+use raster::util::*;
+
+use std::env;
+use std::fs;
+use std::process;
+
+// walls tagged by tex so pieces can be traced back
+fn wall(x0: f32, y0: f32, x1: f32, y1: f32, tag: usize) -> Wall {
+  let mut w = common::wall(x0, y0, x1, y1);
+  w.tex = tag;
+  w.u_offset = 0.25;
+  w
+}
+
+fn order(tree: &BspTree, p: [f32; 2]) -> Vec<usize> {
+  let mut tags = vec![];
+  tree.walk(&p, &mut |w| {
+    tags.push(w.tex);
+    true
+  });
+  tags
+}
+
+fn position(tags: &[usize], tag: usize) -> usize {
+  tags.iter().position(|&t| t == tag).unwrap()
+}
+
+#[test]
+fn split_keeps_texture_in_place() {
+  // a grid of crossing walls, every wall gets split
+  let mut walls = vec![];
+  for i in 0..3 {
+    let k = i as f32 - 1.0;
+    walls.push(wall(-2.0, k, 2.0, k + 0.1, walls.len()));
+    walls.push(wall(k, -2.0, k - 0.1, 2.0, walls.len()));
+  }
+
+  let tree = BspTree::build(&walls, &[]);
+  assert!(tree.walls.len() > walls.len());
+
+  for (source, w) in walls.iter().enumerate() {
+    let start = [w.x0, w.y0];
+    let len = vec_len(&start, &[w.x1, w.y1]);
+
+    let mut pieces: Vec<&Wall> = tree.walls
+      .iter()
+      .zip(tree.sources.iter())
+      .filter(|&(_, &s)| s == source)
+      .map(|(p, _)| p)
+      .collect();
+    pieces.sort_by(|a, b| a.u_offset.partial_cmp(&b.u_offset).unwrap());
+
+    // pieces keep the direction and continue where the previous ended,
+    // u offset grows by the distance from the wall start
+    let mut end = start;
+    let mut total = 0.0;
+    for p in &pieces {
+      assert!(vec_len(&end, &[p.x0, p.y0]) < 1e-4);
+      assert!((p.u_offset - w.u_offset - vec_len(&start, &[p.x0, p.y0])).abs() < 1e-4);
+      end = [p.x1, p.y1];
+      total += vec_len(&[p.x0, p.y0], &[p.x1, p.y1]);
+    }
+    assert!(vec_len(&end, &[w.x1, w.y1]) < 1e-4);
+    assert!((total - len).abs() < 1e-4);
+  }
+}
+
+#[test]
+fn walk_front_to_back() {
+  // parallel walls at x = 1..5, given out of order
+  let walls: Vec<Wall> = [3, 1, 5, 2, 4].iter().map(|&x| wall(x as f32, -1.0, x as f32, 1.0, x)).collect();
+  let tree = BspTree::build(&walls, &[]);
+
+  assert_eq!(order(&tree, [0.0, 0.0]), vec![1, 2, 3, 4, 5]);
+  assert_eq!(order(&tree, [6.0, 0.5]), vec![5, 4, 3, 2, 1]);
+
+  // between walls both sides go outwards
+  let tags = order(&tree, [2.5, 0.0]);
+  assert!(position(&tags, 2) < position(&tags, 1));
+  assert!(position(&tags, 3) < position(&tags, 4));
+  assert!(position(&tags, 4) < position(&tags, 5));
+}
+
+#[test]
+fn walk_stops() {
+  let walls: Vec<Wall> = (0..5).map(|x| wall(x as f32, -1.0, x as f32, 1.0, x)).collect();
+  let tree = BspTree::build(&walls, &[]);
+
+  let mut visited = 0;
+  tree.walk(&[-1.0, 0.0], &mut |_| {
+    visited += 1;
+    visited < 2
+  });
+  assert_eq!(visited, 2);
+}
+
+#[test]
+fn skip() {
+  let walls: Vec<Wall> = (0..4).map(|x| wall(x as f32, -1.0, x as f32, 1.0, x)).collect();
+  let tree = BspTree::build(&walls, &[1, 3]);
+
+  assert_eq!(order(&tree, [-1.0, 0.0]), vec![0, 2]);
+}
+
+#[test]
+fn serialize_round_trip() {
+  let walls = vec![wall(-2.0, 0.0, 2.0, 0.0, 0),
+                   wall(0.0, -2.0, 0.0, 2.0, 1),
+                   wall(1.0, 1.0, 3.0, 2.0, 2),
+                   wall(-1.0, -1.0, -3.0, 1.0, 3)];
+  let tree = BspTree::build(&walls, &[]);
+
+  let path = env::temp_dir().join(format!("raster_bsp_{}.json", process::id()));
+  tree.save(path.to_str().unwrap()).unwrap();
+  let loaded = BspTree::load(path.to_str().unwrap()).unwrap();
+  fs::remove_file(&path).unwrap();
+
+  assert_eq!(serde_json::to_string(&loaded).unwrap(), serde_json::to_string(&tree).unwrap());
+  assert_eq!(loaded.sources, tree.sources);
+  for p in &[[0.5, 0.5], [-1.0, 1.5], [2.5, -1.0]] {
+    assert_eq!(order(&loaded, *p), order(&tree, *p));
+  }
+}
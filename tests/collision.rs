@@ -0,0 +1,87 @@
+extern crate raster;
+extern crate serde_json;
+
+mod common;
+
+use common::*;
+use raster::collision::*;
+use raster::data::*;
+use raster::util::*;
+
+const R: f32 = 0.25;
+
+fn near(a: [f32; 2], b: [f32; 2]) -> bool {
+  vec_len(&a, &b) < 0.01
+}
+
+#[test]
+fn head_on() {
+  let walls = [([1.0, -1.0], [1.0, 1.0])];
+
+  let pos = move_circle(&walls, [0.0, 0.0], [2.0, 0.0], R);
+  assert!(near(pos, [1.0 - R, 0.0]), "{:?}", pos);
+}
+
+#[test]
+fn slide_at_45() {
+  let walls = [([1.0, -10.0], [1.0, 10.0])];
+
+  // hits at y = 0.75, the rest of the move runs along the wall
+  let pos = move_circle(&walls, [0.0, 0.0], [2.0, 2.0], R);
+  assert!(near(pos, [1.0 - R, 2.0]), "{:?}", pos);
+}
+
+#[test]
+fn corner() {
+  let walls = [([1.0, -5.0], [1.0, 1.0]), ([-5.0, 1.0], [1.0, 1.0])];
+
+  let pos = move_circle(&walls, [0.0, 0.0], [3.0, 3.0], R);
+  assert!(near(pos, [1.0 - R, 1.0 - R]), "{:?}", pos);
+
+  // pushing into the corner step by step never gets through
+  let mut pos = [0.0, 0.0];
+  for i in 0..200 {
+    let d = if i % 2 == 0 { [0.3, 0.1] } else { [0.1, 0.3] };
+    pos = move_circle(&walls, pos, d, R);
+    assert!(pos[0] <= 1.0 - R + 0.01 && pos[1] <= 1.0 - R + 0.01, "{:?}", pos);
+  }
+}
+
+#[test]
+fn no_tunneling() {
+  let walls = [([1.0, -1.0], [1.0, 1.0])];
+
+  // a move many times longer than the radius
+  let pos = move_circle(&walls, [0.0, 0.0], [50.0, 0.0], R);
+  assert!(pos[0] < 1.0, "{:?}", pos);
+}
+
+#[test]
+fn low_walls_are_stepped_over() {
+  let mut walls = vec![wall(1.0, -1.0, 1.0, 1.0), wall(2.0, -1.0, 2.0, 1.0), wall(3.0, -1.0, 3.0, 1.0)];
+  // low enough to step over, in the way, high above the head
+  walls[0].h = 0.2;
+  walls[2].z = 1.5;
+
+  let segments = blocking_segments(&walls, &[], &[], 0.0, 0.8, 0.35);
+  assert_eq!(segments, vec![([2.0, -1.0], [2.0, 1.0])]);
+}
+
+#[test]
+fn point_in_square() {
+  let square = [Point2 { x: 0.0, y: 0.0 },
+                Point2 { x: 2.0, y: 0.0 },
+                Point2 { x: 2.0, y: 2.0 },
+                Point2 { x: 0.0, y: 2.0 }];
+
+  assert!(point_in_polygon(&[1.0, 1.0], &square));
+  assert!(!point_in_polygon(&[3.0, 1.0], &square));
+  assert!(!point_in_polygon(&[1.0, -0.5], &square));
+
+  // left and bottom edges are inside, right and top are not, so a
+  // point on an edge shared by two polygons is in exactly one
+  assert!(point_in_polygon(&[0.0, 1.0], &square));
+  assert!(point_in_polygon(&[1.0, 0.0], &square));
+  assert!(!point_in_polygon(&[2.0, 1.0], &square));
+  assert!(!point_in_polygon(&[1.0, 2.0], &square));
+}
@@ -0,0 +1,50 @@
+// helpers shared by the integration tests, not every test uses all
+#![allow(dead_code)]
+
+use raster::data::*;
+use raster::game::*;
+use raster::input::*;
+use raster::texture::*;
+
+use serde_json;
+
+use std::path::Path;
+
+// flat gray 16x16 RGB24 texture data
+static PIXELS: [u8; 16 * 16 * 3] = [128; 16 * 16 * 3];
+
+pub fn data_path(name: &str) -> String {
+  Path::new(env!("CARGO_MANIFEST_DIR")).join("data").join(name).to_str().unwrap().to_owned()
+}
+
+// loads a level file with four gray textures
+pub fn load_path(path: &str) -> Game<'static> {
+  let textures = (0..4).map(|_| Texture::new(&PIXELS, 16, 16, 16 * 3, Format::Rgb24).unwrap()).collect();
+  Game::new(200, 150, textures, path.to_owned()).unwrap()
+}
+
+// loads a level from the data directory
+pub fn load(level: &str) -> Game<'static> {
+  load_path(&data_path(level))
+}
+
+pub fn run(game: &mut Game, ticks: usize, input: Input) {
+  for _ in 0..ticks {
+    game.set_input(input);
+    game.update(TICK);
+  }
+}
+
+pub fn forward() -> Input {
+  Input { forward: 1.0, ..Input::default() }
+}
+
+pub fn wall_json(x0: f32, y0: f32, x1: f32, y1: f32, h: f32, tex: usize) -> String {
+  format!(r#"{{ "x0": {}, "y0": {}, "x1": {}, "y1": {}, "z": 0.0, "h": {}, "color": [255, 255, 255], "tex": {} }}"#,
+          x0, y0, x1, y1, h, tex)
+}
+
+// wall of height 1 on the ground, other fields as a level file leaves them
+pub fn wall(x0: f32, y0: f32, x1: f32, y1: f32) -> Wall {
+  serde_json::from_str(&wall_json(x0, y0, x1, y1, 1.0, 0)).unwrap()
+}
@@ -0,0 +1,86 @@
+extern crate raster;
+extern crate serde_json;
+
+mod common;
+
+use raster::data::*;
+use raster::render::*;
+use raster::texture::*;
+
+fn wall(x0: f32, y0: f32, x1: f32, y1: f32, two_sided: bool) -> Wall {
+  let mut w = common::wall(x0, y0, x1, y1);
+  w.h = 1.5;
+  w.two_sided = two_sided;
+  w
+}
+
+// player at the origin looking along x
+fn player() -> Player {
+  Player {
+    point: Point3 { x: 0.0, y: 0.0, z: 0.8 },
+    dir: 0.0,
+    pitch: 0.0,
+    radius: 0.25,
+    height: 0.8,
+    z_speed: 0.0,
+  }
+}
+
+// draws one wall into a cleared renderer, returns the stats and
+// whether any pixel changed
+fn draw(w: &Wall, player: &Player) -> (FrameStats, bool) {
+  let pixels = vec![255u8; 16 * 16 * 3];
+  let textures = vec![Texture::new(&pixels, 16, 16, 16 * 3, Format::Rgb24).unwrap()];
+  let mut renderer = Renderer::new(200, 150, textures);
+  renderer.buffer.clear();
+
+  let before = (renderer.buffer.pixels.clone(), renderer.buffer.zbuffer.clone());
+  renderer.draw_wall(w, player);
+  let after = (renderer.buffer.pixels.clone(), renderer.buffer.zbuffer.clone());
+
+  (renderer.stats, before != after)
+}
+
+#[test]
+fn in_view() {
+  let (stats, touched) = draw(&wall(3.0, -1.0, 3.0, 1.0, true), &player());
+  assert_eq!((stats.drawn, stats.culled), (1, 0));
+  assert!(touched);
+}
+
+#[test]
+fn outside_frustum() {
+  // in front of the near plane but entirely left or right of the view
+  for w in &[wall(2.0, 4.0, 3.0, 6.0, true), wall(2.0, -4.0, 3.0, -6.0, true)] {
+    let (stats, touched) = draw(w, &player());
+    assert_eq!((stats.drawn, stats.culled), (0, 1));
+    assert!(!touched);
+  }
+
+  // a wall crossing the edge of the view is drawn
+  let (stats, touched) = draw(&wall(2.0, 0.0, 2.0, 6.0, true), &player());
+  assert_eq!((stats.drawn, stats.culled), (1, 0));
+  assert!(touched);
+}
+
+#[test]
+fn one_sided() {
+  // left side of the wall faces the player
+  let front = wall(3.0, -1.0, 3.0, 1.0, false);
+  let (stats, touched) = draw(&front, &player());
+  assert_eq!((stats.drawn, stats.culled), (1, 0));
+  assert!(touched);
+
+  // same wall seen from the other side
+  let mut behind = player();
+  behind.point.x = 6.0;
+  behind.dir = std::f32::consts::PI;
+  let (stats, touched) = draw(&front, &behind);
+  assert_eq!((stats.drawn, stats.culled), (0, 1));
+  assert!(!touched);
+
+  // two sided walls are drawn from both sides
+  let (stats, touched) = draw(&wall(3.0, -1.0, 3.0, 1.0, true), &behind);
+  assert_eq!((stats.drawn, stats.culled), (1, 0));
+  assert!(touched);
+}
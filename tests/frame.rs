@@ -0,0 +1,69 @@
+extern crate raster;
+extern crate serde_json;
+
+mod common;
+
+use common::*;
+use raster::data::*;
+use raster::game::*;
+use raster::input::*;
+use raster::util::*;
+
+// draws the game from the given pose, returns the whole buffer
+fn draw_at(game: &mut Game, player: Player) -> Vec<u8> {
+  let current = game.player;
+  game.player = player;
+  game.draw();
+  game.player = current;
+  game.renderer.buffer.pixels.clone()
+}
+
+fn draw_interpolated(game: &mut Game, k: f32) -> Vec<u8> {
+  game.draw_interpolated(k);
+  game.renderer.buffer.pixels.clone()
+}
+
+#[test]
+fn interpolated() {
+  let mut game = load("map.json");
+  run(&mut game, 10, forward());
+
+  // one more update that moves, turns and looks up
+  let prev = game.player;
+  run(&mut game, 1, Input { forward: 1.0, turn: 1.0, look: 1.0, ..Input::default() });
+  let next = game.player;
+  assert!(next.point.x != prev.point.x && next.dir != prev.dir && next.pitch != prev.pitch);
+
+  let at_prev = draw_at(&mut game, prev);
+  let at_next = draw_at(&mut game, next);
+  assert!(at_prev != at_next);
+
+  assert!(draw_interpolated(&mut game, 0.0) == at_prev);
+  assert!(draw_interpolated(&mut game, 1.0) == at_next);
+
+  let halfway = draw_interpolated(&mut game, 0.5);
+  assert!(halfway != at_prev && halfway != at_next);
+}
+
+#[test]
+fn fit_letterbox() {
+  // wider than needed, bars above and below
+  assert_eq!(fit_rect((200, 150), (800, 700), false), (0, 50, 800, 600));
+}
+
+#[test]
+fn fit_pillarbox() {
+  // taller than needed, bars left and right
+  assert_eq!(fit_rect((200, 150), (1000, 600), false), (100, 0, 800, 600));
+  assert_eq!(fit_rect((200, 150), (200, 150), false), (0, 0, 200, 150));
+}
+
+#[test]
+fn fit_integer() {
+  // 3.5x rounds down to 3x, centered both ways
+  assert_eq!(fit_rect((200, 150), (700, 560), true), (50, 55, 600, 450));
+  assert_eq!(fit_rect((200, 150), (700, 560), false), (0, 17, 700, 525));
+
+  // smaller than the frame, scaled down anyway
+  assert_eq!(fit_rect((200, 150), (100, 100), true), (0, 12, 100, 75));
+}
@@ -0,0 +1,300 @@
+extern crate raster;
+
+use raster::game::*;
+use raster::data::*;
+use raster::headless::*;
+use raster::texture::*;
+
+use std::env;
+use std::f32::consts::PI;
+use std::path::Path;
+use std::path::PathBuf;
+
+const WIDTH: usize = 200;
+const HEIGHT: usize = 150;
+
+// per channel difference allowed
+const TOLERANCE: u8 = 8;
+// share of pixels allowed to differ
+const MAX_DIFF: f32 = 0.005;
+// share allowed between spans and per pixel floors, stepped texture
+// coords flip texels far away where they are smaller than a pixel
+const SPANS_MAX_DIFF: f32 = 0.015;
+
+// 16x16 RGB24 checkers, one color per texture
+fn textures() -> Vec<Vec<u8>> {
+  let colors = [[200, 200, 200], [220, 120, 60], [60, 220, 120], [120, 60, 220]];
+
+  colors.iter()
+    .map(|c| {
+      let mut tex = vec![];
+      for y in 0..16 {
+        for x in 0..16 {
+          let k = if (x / 4 + y / 4) % 2 == 0 { 255 } else { 128 };
+          tex.push((c[0] * k / 255) as u8);
+          tex.push((c[1] * k / 255) as u8);
+          tex.push((c[2] * k / 255) as u8);
+        }
+      }
+      tex
+    })
+    .collect()
+}
+
+fn data_path(name: &str) -> PathBuf {
+  Path::new(env!("CARGO_MANIFEST_DIR")).join("data").join(name)
+}
+
+fn load<'a>(level: &str, textures: &'a [Vec<u8>]) -> Game<'a> {
+  Game::new(WIDTH,
+            HEIGHT,
+            textures.iter()
+              .map(|t| Texture::new(t, 16, 16, 16 * 3, Format::Rgb24).unwrap())
+              .collect(),
+            data_path(level).to_str().unwrap().to_owned())
+    .unwrap()
+}
+
+// goldens come from the per pixel floors, the spans test below
+// keeps the default path close to them
+fn check(name: &str, level: &str, point: Point3, dir: f32) {
+  check_pitch(name, level, point, dir, 0.0);
+}
+
+fn check_pitch(name: &str, level: &str, point: Point3, dir: f32, pitch: f32) {
+  let textures = textures();
+  let mut game = load(level, &textures);
+  game.renderer.spans = false;
+  game.player.pitch = pitch;
+
+  let pixels = render_frame(&mut game, point, dir);
+  let golden = data_path("golden").join(format!("{}.ppm", name));
+
+  if env::var("UPDATE_GOLDEN").is_ok() {
+    write_ppm(&golden, WIDTH, HEIGHT, &pixels).unwrap();
+    return;
+  }
+
+  let (width, height, expected) = read_ppm(&golden).unwrap();
+  assert_eq!((width, height), (WIDTH, HEIGHT));
+
+  let diff = diff_pixels(&pixels, &expected, TOLERANCE);
+  if diff as f32 > (WIDTH * HEIGHT) as f32 * MAX_DIFF {
+    let out = env::temp_dir().join(format!("{}.ppm", name));
+    write_ppm(&out, WIDTH, HEIGHT, &pixels).unwrap();
+    panic!("{}: {} pixels differ, frame written to {}", name, diff, out.display());
+  }
+}
+
+#[test]
+fn start() {
+  check("start", "map.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0);
+}
+
+#[test]
+fn culling() {
+  // some walls of the map are behind the player or off screen
+  let textures = textures();
+  let mut game = load("map.json", &textures);
+  render_frame(&mut game, Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0);
+
+  let stats = game.renderer.stats;
+  assert!(stats.drawn > 0);
+  assert!(stats.culled > 0);
+}
+
+#[test]
+fn back() {
+  check("back", "map.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, PI);
+}
+
+#[test]
+fn outside() {
+  check("outside", "map.json", Point3 { x: -2.0, y: 6.0, z: 0.8 }, -PI / 4.0);
+}
+
+#[test]
+fn raised_floor() {
+  check("raised_floor", "map.json", Point3 { x: 1.5, y: 3.0, z: 0.8 }, PI / 2.0);
+}
+
+#[test]
+fn sectors() {
+  check("sectors", "sectors.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0);
+}
+
+#[test]
+fn sectors_back() {
+  check("sectors_back", "sectors.json", Point3 { x: 9.0, y: 1.5, z: 1.2 }, 3.5);
+}
+
+#[test]
+fn holes() {
+  check("holes", "holes.json", Point3 { x: -1.5, y: 0.0, z: 2.2 }, 0.0);
+}
+
+#[test]
+fn wall_mapping() {
+  // plain, u and v offset, u and v scale, bottom pegged from right to left
+  check("wall_mapping", "mapping.json", Point3 { x: 1.3, y: 0.0, z: 0.65 }, 0.0);
+}
+
+#[test]
+fn floor_mapping() {
+  // offset, scaled and rotated floors from left to right
+  check_pitch("floor_mapping", "mapping.json", Point3 { x: 0.5, y: 0.0, z: 2.2 }, 0.0, -0.9);
+}
+
+#[test]
+fn no_ground() {
+  // platforms over nothing, the ground is off
+  check("no_ground", "void.json", Point3 { x: 3.0, y: 0.0, z: 0.8 }, -0.5);
+}
+
+#[test]
+fn sky() {
+  // panorama under a gradient with clouds over it
+  check_pitch("sky", "sky.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.2, 0.3);
+}
+
+#[test]
+fn clouds_move() {
+  let textures = textures();
+  let mut game = load("sky.json", &textures);
+  game.player.pitch = 0.3;
+  let frame = |game: &mut Game, t: f32| {
+    game.renderer.t = t;
+    render_frame(game, Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.2)
+  };
+
+  // sky.json clouds move 0.05 panoramas per second and repeat twice
+  // around it, after 5 seconds they moved half a texture, which is
+  // one period of the checkers
+  let start = frame(&mut game, 0.0);
+  let moved = frame(&mut game, 2.0);
+  let again = frame(&mut game, 5.0);
+
+  assert!(diff_pixels(&start, &moved, TOLERANCE) as f32 > (WIDTH * HEIGHT) as f32 * MAX_DIFF);
+  assert!(diff_pixels(&start, &again, TOLERANCE) as f32 <= (WIDTH * HEIGHT) as f32 * MAX_DIFF);
+
+  // only the sky changes
+  let below = WIDTH * 3 * (HEIGHT * 3 / 4);
+  assert!(start[below..] == moved[below..]);
+}
+
+#[test]
+fn look_up() {
+  check_pitch("look_up", "map.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0, 0.4);
+}
+
+#[test]
+fn look_down() {
+  check_pitch("look_down", "map.json", Point3 { x: -2.0, y: 6.0, z: 0.8 }, -PI / 4.0, -0.5);
+}
+
+#[test]
+fn spans() {
+  let views = [("map.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0),
+               ("map.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, PI),
+               ("map.json", Point3 { x: -2.0, y: 6.0, z: 0.8 }, -PI / 4.0),
+               ("map.json", Point3 { x: 1.5, y: 3.0, z: 0.8 }, PI / 2.0),
+               ("sectors.json", Point3 { x: 9.0, y: 1.5, z: 1.2 }, 3.5),
+               ("holes.json", Point3 { x: -1.5, y: 0.0, z: 2.2 }, 0.0),
+               ("void.json", Point3 { x: 3.0, y: 0.0, z: 0.8 }, -0.5),
+               ("mapping.json", Point3 { x: 1.3, y: 0.0, z: 0.65 }, 0.0)];
+
+  let textures = textures();
+  for &(level, ref point, dir) in &views {
+    let mut game = load(level, &textures);
+    game.renderer.spans = false;
+    let pixels = render_frame(&mut game, Point3 { ..*point }, dir);
+
+    game.renderer.spans = true;
+    let spans = render_frame(&mut game, Point3 { ..*point }, dir);
+
+    let diff = diff_pixels(&pixels, &spans, TOLERANCE);
+    assert!(diff as f32 <= (WIDTH * HEIGHT) as f32 * SPANS_MAX_DIFF,
+            "{} at {}: {} pixels differ between spans and per pixel floors",
+            level,
+            dir,
+            diff);
+  }
+}
+
+#[test]
+fn threads() {
+  // strips must give exactly the single threaded image
+  let views = [("map.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0),
+               ("map.json", Point3 { x: -2.0, y: 6.0, z: 0.8 }, -PI / 4.0),
+               ("sectors.json", Point3 { x: 9.0, y: 1.5, z: 1.2 }, 3.5)];
+
+  let textures = textures();
+  for &(level, ref point, dir) in &views {
+    let mut game = load(level, &textures);
+    let single = render_frame(&mut game, Point3 { ..*point }, dir);
+
+    for &threads in &[2, 3, 7] {
+      game.renderer.set_threads(threads);
+      let strips = render_frame(&mut game, Point3 { ..*point }, dir);
+      assert!(single == strips, "{} differs with {} threads", level, threads);
+    }
+  }
+}
+
+#[test]
+fn threads_stats() {
+  // every primitive is counted once however the screen is split
+  let views = [("map.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0),
+               ("map.json", Point3 { x: 0.0, y: 0.0, z: 0.8 }, PI),
+               ("map.json", Point3 { x: -2.0, y: 6.0, z: 0.8 }, -PI / 4.0),
+               ("sectors.json", Point3 { x: 9.0, y: 1.5, z: 1.2 }, 3.5)];
+
+  let textures = textures();
+  for &(level, ref point, dir) in &views {
+    let mut game = load(level, &textures);
+    render_frame(&mut game, Point3 { ..*point }, dir);
+    let single = game.renderer.stats;
+
+    game.renderer.set_threads(4);
+    render_frame(&mut game, Point3 { ..*point }, dir);
+    assert_eq!(game.renderer.stats, single, "{} at {}", level, dir);
+  }
+}
+
+#[test]
+fn resize() {
+  let textures = textures();
+  let mut game = load("map.json", &textures);
+  let before = render_frame(&mut game, Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0);
+
+  game.renderer.resize(320, 240);
+  let large = render_frame(&mut game, Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0);
+  assert_eq!(large.len(), 320 * 240 * 3);
+
+  game.renderer.resize(WIDTH, HEIGHT);
+  let after = render_frame(&mut game, Point3 { x: 0.0, y: 0.0, z: 0.8 }, 0.0);
+  assert!(before == after);
+}
+
+#[test]
+fn fov() {
+  let textures = textures();
+  let mut game = load("map.json", &textures);
+  assert!((game.renderer.fov() - 90.0).abs() < 1e-3);
+
+  for &fov in &[10.0, 60.0, 120.0, 170.0] {
+    assert_eq!(game.renderer.set_fov(fov), fov);
+    assert!((game.renderer.fov() - fov).abs() < 1e-3, "{} read back as {}", fov, game.renderer.fov());
+  }
+
+  // out of range values are clamped
+  assert_eq!(game.renderer.set_fov(5.0), 10.0);
+  assert!((game.renderer.fov() - 10.0).abs() < 1e-3);
+  assert_eq!(game.renderer.set_fov(179.0), 170.0);
+  assert!((game.renderer.fov() - 170.0).abs() < 1e-3);
+
+  // resizing keeps it
+  game.renderer.set_fov(75.0);
+  game.renderer.resize(320, 240);
+  assert!((game.renderer.fov() - 75.0).abs() < 1e-3);
+}
@@ -0,0 +1,117 @@
+extern crate raster;
+
+use raster::input::*;
+
+// synthetic controller events, no hardware needed
+fn gamepad(events: Vec<PadEvent>) -> Gamepad {
+  let mut gamepad = Gamepad::default();
+  for e in events {
+    gamepad.event(e);
+  }
+  gamepad
+}
+
+fn apply(gamepad: &Gamepad, config: &Config) -> Input {
+  let mut input = Input::default();
+  gamepad.apply(&mut input, config);
+  input
+}
+
+fn pad(events: Vec<PadEvent>) -> Input {
+  apply(&gamepad(events), &Config::default())
+}
+
+#[test]
+fn dead_zone() {
+  let input = pad(vec![PadEvent::Axis(Axis::LeftX, 3000), PadEvent::Axis(Axis::RightY, -5000)]);
+  assert_eq!(input.strafe, 0.0);
+  assert_eq!(input.look, 0.0);
+}
+
+#[test]
+fn sticks() {
+  let input = pad(vec![PadEvent::Axis(Axis::LeftY, -32767), PadEvent::Axis(Axis::RightX, 32767)]);
+  assert_eq!(input.forward, 1.0);
+  assert_eq!(input.turn, -1.0);
+
+  // halfway past the dead zone
+  let input = pad(vec![PadEvent::Axis(Axis::LeftX, (32767.0 * 0.6) as i16)]);
+  assert!((input.strafe - 0.5).abs() < 0.001);
+}
+
+#[test]
+fn buttons() {
+  let input = pad(vec![PadEvent::Button("a".to_owned(), true)]);
+  assert!(input.jump);
+
+  let input = pad(vec![PadEvent::Button("a".to_owned(), true), PadEvent::Button("a".to_owned(), false)]);
+  assert!(!input.jump);
+}
+
+#[test]
+fn invert() {
+  let up = gamepad(vec![PadEvent::Axis(Axis::RightY, -32767)]);
+  let mut config = Config::default();
+  assert_eq!(apply(&up, &config).look, 1.0);
+
+  // the mouse setting leaves the stick alone
+  config.mouse_invert = true;
+  assert_eq!(apply(&up, &config).look, 1.0);
+
+  config.pad_invert = true;
+  assert_eq!(apply(&up, &config).look, -1.0);
+}
+
+#[test]
+fn reset() {
+  let mut held = gamepad(vec![PadEvent::Axis(Axis::LeftY, -32767), PadEvent::Button("a".to_owned(), true)]);
+  held.reset();
+
+  let input = apply(&held, &Config::default());
+  assert_eq!(input.forward, 0.0);
+  assert!(!input.jump);
+  assert!(!held.is_down("a"));
+}
+
+#[test]
+fn mouse() {
+  let mut config = Config { mouse_sensitivity: 0.01, ..Config::default() };
+
+  // right and down turn right and look down
+  let mut input = Input::default();
+  input.mouse_motion(10, 20, &config);
+  assert!((input.turn_delta + 0.1).abs() < 1e-6);
+  assert!((input.look_delta + 0.2).abs() < 1e-6);
+
+  // motion adds up and scales with the sensitivity
+  config.mouse_sensitivity = 0.02;
+  input.mouse_motion(10, 20, &config);
+  assert!((input.turn_delta + 0.3).abs() < 1e-6);
+  assert!((input.look_delta + 0.6).abs() < 1e-6);
+}
+
+#[test]
+fn mouse_invert() {
+  let config = Config { mouse_invert: true, ..Config::default() };
+
+  let mut input = Input::default();
+  input.mouse_motion(10, 20, &config);
+  assert_eq!(input.turn_delta, -10.0 * config.mouse_sensitivity);
+  assert_eq!(input.look_delta, 20.0 * config.mouse_sensitivity);
+}
+
+#[test]
+fn keys() {
+  let input = Config::default().input(|k| k == "W" || k == "Left");
+  assert_eq!(input.forward, 1.0);
+  assert_eq!(input.turn, 1.0);
+}
+
+#[test]
+fn config_file() {
+  let path = concat!(env!("CARGO_MANIFEST_DIR"), "/data/config.json");
+  let config = Config::load(path).unwrap();
+  assert_eq!(config.fov, Config::default().fov);
+  assert_eq!(config.dead_zone, Config::default().dead_zone);
+  assert_eq!(config.pad_invert, Config::default().pad_invert);
+}
@@ -0,0 +1,220 @@
+extern crate raster;
+extern crate serde_json;
+
+mod common;
+
+use common::*;
+use raster::data::*;
+use raster::level::*;
+use raster::util::*;
+
+use std::env;
+use std::fs::File;
+use std::fs;
+use std::io::prelude::*;
+use std::process;
+
+const TEXTURES: usize = 4;
+
+fn points(points: &[(f32, f32)]) -> String {
+  let points: Vec<String> = points.iter().map(|&(x, y)| format!(r#"{{ "x": {}, "y": {} }}"#, x, y)).collect();
+  format!("[{}]", points.join(", "))
+}
+
+fn holed(outline: &[(f32, f32)], holes: &[&[(f32, f32)]]) -> String {
+  let holes: Vec<String> = holes.iter().map(|h| points(h)).collect();
+  format!(r#"{{ "points": {}, "holes": [{}], "height": 0.5, "color": [0, 0, 0], "tex": 0 }}"#,
+          points(outline),
+          holes.join(", "))
+}
+
+fn floor(outline: &[(f32, f32)]) -> String {
+  holed(outline, &[])
+}
+
+fn level(walls: &[String], floors: &[String]) -> String {
+  format!(r#"{{ "walls": [{}], "floors": [{}], "sprites": [] }}"#, walls.join(", "), floors.join(", "))
+}
+
+// loads json through a file only this test uses
+fn load(name: &str, json: &str) -> Result<Level, LevelError> {
+  let path = env::temp_dir().join(format!("raster_level_{}_{}.json", name, process::id()));
+  File::create(&path).unwrap().write_all(json.as_bytes()).unwrap();
+
+  let level = Level::load(path.to_str().unwrap(), TEXTURES);
+  fs::remove_file(&path).unwrap();
+  level
+}
+
+// field paths of all problems reported
+fn problems(name: &str, json: &str) -> Vec<String> {
+  match load(name, json) {
+    Err(LevelError::Invalid(_, problems)) => problems.into_iter().map(|p| p.field).collect(),
+    Err(e) => panic!("{}: unexpected error {}", name, e),
+    Ok(_) => panic!("{}: level loaded", name),
+  }
+}
+
+fn square() -> String {
+  floor(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+}
+
+#[test]
+fn valid() {
+  let json = level(&[wall_json(0.0, 0.0, 1.0, 0.0, 1.0, 3)], &[square()]);
+  assert!(load("valid", &json).is_ok());
+}
+
+#[test]
+fn tex_out_of_range() {
+  let json = level(&[wall_json(0.0, 0.0, 1.0, 0.0, 1.0, 0), wall_json(0.0, 0.0, 1.0, 0.0, 1.0, TEXTURES)],
+                   &[]);
+  assert_eq!(problems("tex", &json), vec!["walls[1].tex"]);
+}
+
+#[test]
+fn zero_length_wall() {
+  let json = level(&[wall_json(1.0, 2.0, 1.0, 2.0, 1.0, 0)], &[]);
+  assert_eq!(problems("zero_length", &json), vec!["walls[0]"]);
+}
+
+#[test]
+fn wall_height() {
+  let json = level(&[wall_json(0.0, 0.0, 1.0, 0.0, 0.0, 0), wall_json(0.0, 0.0, 1.0, 0.0, -1.0, 0)], &[]);
+  assert_eq!(problems("height", &json), vec!["walls[0].h", "walls[1].h"]);
+}
+
+#[test]
+fn floor_points() {
+  let json = level(&[], &[square(), floor(&[(0.0, 0.0), (1.0, 0.0)])]);
+  assert_eq!(problems("points", &json), vec!["floors[1].points"]);
+}
+
+#[test]
+fn self_intersecting_floor() {
+  // bow tie
+  let json = level(&[], &[floor(&[(0.0, 0.0), (1.0, 1.0), (1.0, 0.0), (0.0, 1.0)])]);
+  assert_eq!(problems("bow_tie", &json), vec!["floors[0].points"]);
+}
+
+#[test]
+fn holes() {
+  let outline = [(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)];
+  let inside: &[(f32, f32)] = &[(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0)];
+  let apart: &[(f32, f32)] = &[(3.0, 3.0), (3.5, 3.0), (3.5, 3.5)];
+  let crossing: &[(f32, f32)] = &[(3.0, 1.0), (5.0, 1.0), (5.0, 2.0), (3.0, 2.0)];
+  let outside: &[(f32, f32)] = &[(5.0, 1.0), (6.0, 1.0), (6.0, 2.0)];
+  let overlapping: &[(f32, f32)] = &[(1.5, 1.5), (2.5, 1.5), (2.5, 2.5)];
+  let enclosing: &[(f32, f32)] = &[(0.5, 0.5), (3.0, 0.5), (3.0, 2.5), (0.5, 2.5)];
+
+  let json = level(&[], &[holed(&outline, &[inside, apart])]);
+  assert!(load("holes", &json).is_ok());
+
+  let json = level(&[],
+                   &[holed(&outline, &[inside, crossing]),
+                     holed(&outline, &[outside]),
+                     holed(&outline, &[inside, apart, overlapping]),
+                     holed(&outline, &[inside, enclosing])]);
+  assert_eq!(problems("bad_holes", &json),
+             vec!["floors[0].holes[1]", "floors[1].holes[0]", "floors[2].holes[2]", "floors[3].holes[1]"]);
+}
+
+// unit square sector, the first edge leads into sector portal
+fn sector(portal: usize) -> String {
+  format!(r#"{{ "edges": [{{ "x": 0, "y": 0, "portal": {} }}, {{ "x": 1, "y": 0 }}, {{ "x": 1, "y": 1 }}, {{ "x": 0, "y": 1 }}],
+                 "floor": 0.0, "ceiling": 2.0, "floor_tex": 0, "ceiling_tex": 0, "wall_tex": 0, "color": [0, 0, 0] }}"#,
+          portal)
+}
+
+#[test]
+fn portals() {
+  let json = format!(r#"{{ "walls": [], "floors": [], "sprites": [], "sectors": [{}, {}, {}] }}"#,
+                     sector(1),
+                     sector(1),
+                     sector(5));
+
+  match load("portals", &json) {
+    Err(LevelError::Invalid(_, problems)) => {
+      let found: Vec<(&str, &str)> = problems.iter().map(|p| (p.field.as_str(), p.message.as_str())).collect();
+      assert_eq!(found,
+                 vec![("sectors[1].edges[0].portal", "portal back into its own sector"),
+                      ("sectors[2].edges[0].portal", "portal to a missing sector")]);
+    }
+    _ => panic!("expected an invalid level"),
+  }
+}
+
+#[test]
+fn sector_mapping() {
+  // sector walls and planes have no texture mapping
+  let mapped = sector(1).replace(r#""x": 1, "y": 0 }"#, r#""x": 1, "y": 0, "u_offset": 0.5 }"#);
+  let json = format!(r#"{{ "walls": [], "floors": [], "sprites": [], "sectors": [{}, {}] }}"#, sector(1), mapped);
+  assert_eq!(problems("sector_mapping", &json), vec!["sectors"]);
+
+  let mapped = sector(1).replace(r#""wall_tex": 0"#, r#""wall_tex": 0, "rotation": 45"#);
+  let json = format!(r#"{{ "walls": [], "floors": [], "sprites": [], "sectors": [{}] }}"#, mapped);
+  assert_eq!(problems("sector_rotation", &json), vec!["sectors"]);
+}
+
+#[test]
+fn nan_coordinate() {
+  // json has no nan, check the parsed level directly
+  let mut walls = vec![wall(0.0, 0.0, 1.0, 0.0)];
+  walls[0].y1 = f32::NAN;
+
+  let level = Level {
+    walls: walls,
+    floors: vec![],
+    sprites: vec![],
+    sky: None,
+    environment: Environment::default(),
+    sectors: vec![],
+    movers: vec![],
+  };
+
+  let fields: Vec<String> = level.validate(TEXTURES).into_iter().map(|p| p.field).collect();
+  assert_eq!(fields, vec!["walls[0]"]);
+}
+
+#[test]
+fn all_problems_reported() {
+  let json = level(&[wall_json(0.0, 0.0, 0.0, 0.0, 1.0, 9), wall_json(0.0, 0.0, 1.0, 0.0, -1.0, 0)],
+                   &[floor(&[(0.0, 0.0), (1.0, 0.0)]),
+                     floor(&[(0.0, 0.0), (1.0, 1.0), (1.0, 0.0), (0.0, 1.0)])]);
+
+  assert_eq!(problems("many", &json),
+             vec!["walls[0]", "walls[0].tex", "walls[1].h", "floors[0].points", "floors[1].points"]);
+}
+
+#[test]
+fn bad_section_type() {
+  // the other sections are still checked
+  let json = r#"{ "walls": {}, "floors": [], "sprites": [ { "point": { "x": 0, "y": 0, "z": 0 }, "color": [0, 0, 0], "tex": 7 } ] }"#;
+  assert_eq!(problems("section", json), vec!["walls", "sprites[0].tex"]);
+}
+
+#[test]
+fn syntax_and_io_errors() {
+  match load("syntax", "{ \"walls\": [") {
+    Err(LevelError::Syntax(..)) => {}
+    _ => panic!("expected a syntax error"),
+  }
+
+  match Level::load("missing/level.json", TEXTURES) {
+    Err(LevelError::Io(ref path, _)) => assert_eq!(path, "missing/level.json"),
+    _ => panic!("expected an io error"),
+  }
+}
+
+#[test]
+fn intersections() {
+  let (a, b) = ([0.0, 0.0], [2.0, 2.0]);
+
+  assert!(segments_intersect(&a, &b, &[0.0, 2.0], &[2.0, 0.0]));
+  assert!(!segments_intersect(&a, &b, &[1.0, 0.0], &[3.0, 2.0]));
+  // touching at an end point
+  assert!(segments_intersect(&a, &b, &[2.0, 2.0], &[3.0, 0.0]));
+  // collinear, overlapping and apart
+  assert!(segments_intersect(&a, &b, &[1.0, 1.0], &[3.0, 3.0]));
+  assert!(!segments_intersect(&a, &b, &[3.0, 3.0], &[4.0, 4.0]));
+}
@@ -0,0 +1,111 @@
+extern crate raster;
+extern crate serde_json;
+
+mod common;
+
+use raster::data::*;
+use raster::render::*;
+use raster::texture::*;
+
+// a texture repeats every half world unit
+const REPEAT: f32 = 0.5;
+
+// 16x16 texture with a different color in every texel
+fn texels() -> Vec<u8> {
+  let mut pixels = vec![];
+  for y in 0..16 {
+    for x in 0..16 {
+      pixels.extend_from_slice(&[x * 16, y * 16, 128]);
+    }
+  }
+  pixels
+}
+
+// wall right in front of the player looking along x
+fn wall() -> Wall {
+  let mut w = common::wall(2.0, 1.0, 2.0, -1.0);
+  w.z = 0.2;
+  w.h = 1.0;
+  w
+}
+
+fn draw(w: &Wall) -> Vec<u8> {
+  let pixels = texels();
+  let textures = vec![Texture::new(&pixels, 16, 16, 16 * 3, Format::Rgb24).unwrap()];
+  let mut renderer = Renderer::new(200, 150, textures);
+  renderer.buffer.clear();
+
+  let player = Player {
+    point: Point3 { x: 0.0, y: 0.0, z: 0.8 },
+    dir: 0.0,
+    pitch: 0.0,
+    radius: 0.25,
+    height: 0.8,
+    z_speed: 0.0,
+  };
+  renderer.draw_wall(w, &player);
+  renderer.buffer.pixels.clone()
+}
+
+// a few pixels may round to the next texel, the mappings that differ
+// change thousands
+fn same(a: &[u8], b: &[u8]) -> bool {
+  a.chunks(3).zip(b.chunks(3)).filter(|&(p, q)| p != q).count() < 50
+}
+
+#[test]
+fn offsets_are_world_units() {
+  let plain = draw(&wall());
+
+  let mut w = wall();
+  w.u_offset = REPEAT;
+  assert!(same(&draw(&w), &plain));
+  w.u_offset = REPEAT / 4.0;
+  assert!(!same(&draw(&w), &plain));
+
+  let mut w = wall();
+  w.v_offset = REPEAT;
+  assert!(same(&draw(&w), &plain));
+  w.v_offset = REPEAT / 4.0;
+  assert!(!same(&draw(&w), &plain));
+}
+
+#[test]
+fn scales() {
+  let plain = draw(&wall());
+
+  // a texture twice as wide repeats every other half unit, same as
+  // moving by a whole repeat once stretched
+  let mut w = wall();
+  w.u_scale = 2.0;
+  let stretched = draw(&w);
+  assert!(!same(&stretched, &plain));
+  w.u_offset = 2.0 * REPEAT;
+  assert!(same(&draw(&w), &stretched));
+
+  let mut w = wall();
+  w.v_scale = 0.5;
+  let squashed = draw(&w);
+  assert!(!same(&squashed, &plain));
+  w.v_offset = REPEAT / 2.0;
+  assert!(same(&draw(&w), &squashed));
+}
+
+#[test]
+fn peg() {
+  // a whole number of repeats high, both edges line up
+  let mut w = wall();
+  w.peg = Peg::Bottom;
+  assert!(same(&draw(&w), &draw(&wall())));
+
+  let mut top = wall();
+  top.h = 1.3 * REPEAT;
+  let mut bottom = wall();
+  bottom.h = 1.3 * REPEAT;
+  bottom.peg = Peg::Bottom;
+  assert!(!same(&draw(&bottom), &draw(&top)));
+
+  // bottom pegged lines up with a top pegged wall moved by the rest
+  top.v_offset = -0.3 * REPEAT;
+  assert!(same(&draw(&bottom), &draw(&top)));
+}
@@ -0,0 +1,83 @@
+extern crate raster;
+extern crate serde_json;
+
+mod common;
+
+use common::*;
+use raster::input::*;
+
+fn action() -> Input {
+  Input { action: true, ..Input::default() }
+}
+
+#[test]
+fn closed_door_blocks() {
+  let mut game = load("movers.json");
+  game.player.point.x = 0.0;
+
+  run(&mut game, 120, forward());
+  assert!(game.player.point.x < 2.0 - game.player.radius + 0.01);
+}
+
+#[test]
+fn door_opens() {
+  let mut game = load("movers.json");
+  game.player.point.x = 1.2;
+
+  run(&mut game, 1, action());
+  run(&mut game, 90, Input::default());
+  run(&mut game, 60, forward());
+  assert!(game.player.point.x > 2.5);
+}
+
+#[test]
+fn door_out_of_range() {
+  let mut game = load("movers.json");
+  game.player.point.x = 0.0;
+
+  run(&mut game, 1, action());
+  run(&mut game, 90, Input::default());
+  run(&mut game, 120, forward());
+  assert!(game.player.point.x < 2.0);
+}
+
+#[test]
+fn lift_carries_player() {
+  let mut game = load("movers.json");
+  game.player.point.x = 3.5;
+  let start = game.player.point.z;
+
+  // held action only triggers once
+  run(&mut game, 130, action());
+  assert!((game.player.point.z - start - 1.0).abs() < 0.01);
+
+  // back down after the wait
+  run(&mut game, 300, Input::default());
+  assert!((game.player.point.z - start).abs() < 0.01);
+}
+
+#[test]
+fn activate_by_id() {
+  let mut game = load("movers.json");
+  game.player.point.x = 0.0;
+
+  assert!(game.activate("door"));
+  assert!(!game.activate("missing"));
+  run(&mut game, 90, Input::default());
+  run(&mut game, 120, forward());
+  assert!(game.player.point.x > 2.5);
+}
+
+#[test]
+fn gate_slides() {
+  let mut game = load("movers.json");
+  game.player.point.x = 4.5;
+
+  run(&mut game, 60, forward());
+  assert!(game.player.point.x < 5.0);
+
+  run(&mut game, 1, action());
+  run(&mut game, 120, Input::default());
+  run(&mut game, 60, forward());
+  assert!(game.player.point.x > 5.3);
+}
@@ -0,0 +1,96 @@
+extern crate raster;
+extern crate serde_json;
+
+mod common;
+
+use common::*;
+use raster::game::*;
+use raster::input::*;
+
+// flat ground with a 0.3 step at x 1..3 and a 1.0 block at x 5..7
+fn steps() -> Game<'static> {
+  load("steps.json")
+}
+
+fn jump() -> Input {
+  Input { jump: true, ..Input::default() }
+}
+
+fn feet(game: &Game) -> f32 {
+  game.player.point.z - game.player.height
+}
+
+#[test]
+fn step_up() {
+  let mut game = steps();
+
+  run(&mut game, 45, forward());
+  assert!(game.player.point.x > 1.0 && game.player.point.x < 3.0);
+  assert!((feet(&game) - 0.3).abs() < 0.001);
+}
+
+#[test]
+fn blocked_by_high_floor() {
+  let mut game = steps();
+  game.player.point.x = 4.0;
+
+  run(&mut game, 60, forward());
+  assert!(game.player.point.x < 5.0 - game.player.radius + 0.01);
+  assert!(feet(&game).abs() < 0.001);
+}
+
+#[test]
+fn fall_off_ledge() {
+  let mut game = steps();
+  game.player.point.x = 6.0;
+  game.player.point.z = 1.0 + game.player.height;
+  game.player.dir = std::f32::consts::PI;
+
+  // still on top right after walking off the edge, then down
+  run(&mut game, 35, forward());
+  assert!(game.player.point.x < 5.0);
+  assert!(feet(&game) > 0.0 && feet(&game) < 1.0);
+
+  run(&mut game, 60, Input::default());
+  assert!(feet(&game).abs() < 0.001);
+  assert_eq!(game.player.z_speed, 0.0);
+}
+
+#[test]
+fn land() {
+  let mut game = steps();
+  game.player.point.x = 2.0;
+  game.player.point.z = 3.0;
+
+  run(&mut game, 60, Input::default());
+  assert!((feet(&game) - 0.3).abs() < 0.001);
+  assert_eq!(game.player.z_speed, 0.0);
+}
+
+#[test]
+fn jump_only_from_ground() {
+  let mut game = steps();
+
+  run(&mut game, 1, jump());
+  assert!(game.player.z_speed > 0.0);
+
+  // holding jump does not jump again until landed
+  let mut top = feet(&game);
+  let mut landed = false;
+  for _ in 0..120 {
+    let before = game.player.z_speed;
+    run(&mut game, 1, jump());
+    if feet(&game) > 0.0 {
+      assert!(game.player.z_speed < before);
+    } else {
+      landed = true;
+      break;
+    }
+    top = f32::max(top, feet(&game));
+  }
+  assert!(landed);
+
+  // a jump reaches v^2 / 2g
+  let expected = game.jump_speed * game.jump_speed / (2.0 * game.gravity);
+  assert!((top - expected).abs() < 0.05, "{}", top);
+}
@@ -0,0 +1,57 @@
+extern crate raster;
+extern crate serde_json;
+
+mod common;
+
+use common::*;
+use raster::game::*;
+use raster::headless::*;
+use raster::input::*;
+use raster::replay::*;
+
+use std::env;
+use std::fs;
+use std::process;
+
+// walks forward, turns with the mouse and jumps
+fn play(game: &mut Game, recording: &mut Recording) {
+  for i in 0..120 {
+    let input = Input {
+      forward: 1.0,
+      strafe: if i < 60 { 0.5 } else { -0.25 },
+      turn_delta: 0.01,
+      jump: i == 30,
+      ..Input::default()
+    };
+
+    game.set_input(input);
+    recording.record(game);
+    game.update(TICK);
+  }
+}
+
+#[test]
+fn replay() {
+  let level = data_path("map.json");
+  let mut game = load_path(&level);
+  let mut recording = Recording::new(&level, TICK);
+  play(&mut game, &mut recording);
+  let expected = current_frame(&mut game);
+
+  let path = env::temp_dir().join(format!("raster_replay_{}.json", process::id()));
+  recording.save(path.to_str().unwrap()).unwrap();
+  let recording = Recording::load(path.to_str().unwrap()).unwrap();
+  fs::remove_file(&path).unwrap();
+  assert_eq!(recording.inputs.len(), 120);
+
+  let mut replayed = load_path(&recording.level);
+  let mut updates = 0;
+  recording.replay(&mut replayed, |_, _| updates += 1);
+  assert_eq!(updates, 120);
+
+  assert_eq!(replayed.player.point.x, game.player.point.x);
+  assert_eq!(replayed.player.point.y, game.player.point.y);
+  assert_eq!(replayed.player.point.z, game.player.point.z);
+  assert_eq!(replayed.player.dir, game.player.dir);
+  assert!(current_frame(&mut replayed) == expected);
+}
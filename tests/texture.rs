@@ -0,0 +1,65 @@
+extern crate raster;
+
+use raster::texture::*;
+
+#[test]
+fn pitch() {
+  // 2x2 RGB rows padded to 8 bytes, padding must never be read
+  let pixels = [1, 2, 3, 4, 5, 6, 99, 99,
+                7, 8, 9, 10, 11, 12, 99, 99];
+  let tex = Texture::new(&pixels, 2, 2, 8, Format::Rgb24).unwrap();
+
+  assert_eq!(tex.texel(0, 0), [1, 2, 3, 255]);
+  assert_eq!(tex.texel(1, 0), [4, 5, 6, 255]);
+  assert_eq!(tex.texel(0, 1), [7, 8, 9, 255]);
+  assert_eq!(tex.texel(1, 1), [10, 11, 12, 255]);
+
+  // the last row needs no padding
+  assert!(Texture::new(&pixels[..14], 2, 2, 8, Format::Rgb24).is_ok());
+}
+
+#[test]
+fn wraps() {
+  let pixels = [1, 2, 3, 4, 5, 6];
+  let tex = Texture::new(&pixels, 2, 1, 6, Format::Rgb24).unwrap();
+
+  assert_eq!(tex.texel(-1, 0), tex.texel(1, 0));
+  assert_eq!(tex.texel(2, 3), tex.texel(0, 0));
+  assert_eq!(tex.sample(0.75, 0.0), [4, 5, 6, 255]);
+  assert_eq!(tex.sample(-0.25, 0.0), [4, 5, 6, 255]);
+}
+
+#[test]
+fn rgba() {
+  let pixels = [1, 2, 3, 0, 4, 5, 6, 128];
+  let tex = Texture::new(&pixels, 2, 1, 8, Format::Rgba32).unwrap();
+
+  assert_eq!(tex.texel(0, 0), [1, 2, 3, 0]);
+  assert_eq!(tex.texel(1, 0), [4, 5, 6, 128]);
+}
+
+#[test]
+fn indexed() {
+  let pixels = [1, 0, 7, 0];
+  let palette = vec![[10, 20, 30], [40, 50, 60]];
+  let tex = Texture::new(&pixels, 2, 2, 2, Format::Indexed(palette)).unwrap();
+
+  assert_eq!(tex.texel(0, 0), [40, 50, 60, 255]);
+  assert_eq!(tex.texel(1, 0), [10, 20, 30, 255]);
+  // indices past the palette are black
+  assert_eq!(tex.texel(0, 1), [0, 0, 0, 255]);
+}
+
+#[test]
+fn rejects_bad_sizes() {
+  let pixels = [0u8; 12];
+
+  assert!(Texture::new(&pixels, 2, 2, 6, Format::Rgb24).is_ok());
+  // one byte short
+  assert!(Texture::new(&pixels[..11], 2, 2, 6, Format::Rgb24).is_err());
+  assert!(Texture::new(&pixels, 2, 2, 8, Format::Rgba32).is_err());
+  // rows overlap
+  assert!(Texture::new(&pixels, 2, 2, 5, Format::Rgb24).is_err());
+  assert!(Texture::new(&pixels, 0, 2, 6, Format::Rgb24).is_err());
+  assert!(Texture::new(&pixels, 2, 0, 6, Format::Rgb24).is_err());
+}
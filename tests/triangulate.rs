@@ -0,0 +1,80 @@
+extern crate raster;
+
+use raster::triangulate::*;
+
+fn area(t: &[[f32; 2]; 3]) -> f32 {
+  ((t[1][0] - t[0][0]) * (t[2][1] - t[0][1]) - (t[1][1] - t[0][1]) * (t[2][0] - t[0][0])) / 2.0
+}
+
+// total area, every triangle must be counterclockwise
+fn total(triangles: &[[[f32; 2]; 3]]) -> f32 {
+  triangles.iter().fold(0.0, |sum, t| {
+    assert!(area(t) >= 0.0, "clockwise triangle {:?}", t);
+    sum + area(t)
+  })
+}
+
+fn square(x: f32, y: f32, size: f32) -> Vec<[f32; 2]> {
+  vec![[x, y], [x + size, y], [x + size, y + size], [x, y + size]]
+}
+
+#[test]
+fn convex() {
+  let hexagon: Vec<[f32; 2]> = (0..6)
+    .map(|i| {
+      let a = i as f32 * std::f32::consts::PI / 3.0;
+      [f32::cos(a), f32::sin(a)]
+    })
+    .collect();
+
+  let triangles = triangulate(&hexagon, &[]).unwrap();
+  assert_eq!(triangles.len(), 4);
+  assert!((total(&triangles) - 3.0 * f32::sqrt(3.0) / 2.0).abs() < 1e-4);
+}
+
+#[test]
+fn concave() {
+  let l = vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [1.0, 1.0], [1.0, 2.0], [0.0, 2.0]];
+
+  let triangles = triangulate(&l, &[]).unwrap();
+  assert_eq!(triangles.len(), 4);
+  assert!((total(&triangles) - 3.0).abs() < 1e-4);
+
+  // clockwise input gives the same area
+  let mut cw = l.clone();
+  cw.reverse();
+  assert!((total(&triangulate(&cw, &[]).unwrap()) - 3.0).abs() < 1e-4);
+}
+
+#[test]
+fn one_hole() {
+  let triangles = triangulate(&square(0.0, 0.0, 4.0), &[square(1.0, 1.0, 2.0)]).unwrap();
+  assert!((total(&triangles) - 12.0).abs() < 1e-4);
+}
+
+#[test]
+fn two_holes() {
+  let holes = [square(1.0, 1.0, 2.0), vec![[5.0, 5.0], [8.0, 5.0], [8.0, 9.0]]];
+
+  let triangles = triangulate(&square(0.0, 0.0, 10.0), &holes).unwrap();
+  assert!((total(&triangles) - (100.0 - 4.0 - 6.0)).abs() < 1e-3);
+}
+
+#[test]
+fn hole_behind_reflex_vertex() {
+  // the ray from the hole hits the edge next to a notch pointing at it
+  let outline = vec![[0.0, 0.0], [10.0, 0.0], [10.0, 4.0], [6.0, 5.0], [10.0, 6.0], [10.0, 10.0], [0.0, 10.0]];
+
+  let triangles = triangulate(&outline, &[square(2.0, 4.0, 2.0)]).unwrap();
+  assert!((total(&triangles) - (100.0 - 4.0 - 4.0)).abs() < 1e-3);
+}
+
+#[test]
+fn degenerate() {
+  // all points on a line
+  assert!(triangulate(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]], &[]).is_none());
+  // hole too small
+  assert!(triangulate(&square(0.0, 0.0, 4.0), &[vec![[1.0, 1.0], [2.0, 2.0]]]).is_none());
+  // nothing to bridge the hole to
+  assert!(triangulate(&square(0.0, 0.0, 4.0), &[square(5.0, 1.0, 1.0)]).is_none());
+}